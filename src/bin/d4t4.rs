@@ -0,0 +1,95 @@
+//! Formats d4t4 files in place, or stdin to stdout when no files are given.
+//!
+//! ```text
+//! d4t4 fmt [--check] [FILE]...
+//! ```
+//!
+//! With `--check` nothing is written, the files that aren't formatted are listed and the
+//! exit code is 1. Files that fail to parse are reported with exit code 2.
+
+use std::fs;
+use std::io::{self, Read, Write};
+use std::process;
+
+const USAGE: &str = "Usage: d4t4 fmt [--check] [FILE]...";
+
+fn main() {
+    let mut args = std::env::args().skip(1);
+    if args.next().as_deref() != Some("fmt") {
+        eprintln!("{}", USAGE);
+        process::exit(2);
+    }
+    let mut check = false;
+    let mut paths = Vec::new();
+    for arg in args {
+        match arg.as_str() {
+            "--check" => check = true,
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return;
+            }
+            _ if arg.starts_with('-') => {
+                eprintln!("Unknown option {}\n{}", arg, USAGE);
+                process::exit(2);
+            }
+            _ => paths.push(arg),
+        }
+    }
+    process::exit(if paths.is_empty() {
+        format_stdin(check)
+    } else {
+        paths
+            .iter()
+            .map(|path| format_file(path, check))
+            .max()
+            .unwrap_or(0)
+    });
+}
+
+fn format_stdin(check: bool) -> i32 {
+    let mut input = String::new();
+    if let Err(error) = io::stdin().read_to_string(&mut input) {
+        eprintln!("Failed to read stdin: {}", error);
+        return 2;
+    }
+    match d4t4::format(&input) {
+        Ok(formatted) if check => (formatted != input) as i32,
+        Ok(formatted) => {
+            let _ = io::stdout().write_all(formatted.as_bytes());
+            0
+        }
+        Err(error) => report(&error, &input, "<stdin>"),
+    }
+}
+
+fn format_file(path: &str, check: bool) -> i32 {
+    let input = match fs::read_to_string(path) {
+        Ok(input) => input,
+        Err(error) => {
+            eprintln!("Failed to read {}: {}", path, error);
+            return 2;
+        }
+    };
+    let formatted = match d4t4::format(&input) {
+        Ok(formatted) => formatted,
+        Err(error) => return report(&error, &input, path),
+    };
+    if formatted == input {
+        return 0;
+    }
+    if check {
+        println!("{}", path);
+        return 1;
+    }
+    if let Err(error) = fs::write(path, formatted) {
+        eprintln!("Failed to write {}: {}", path, error);
+        return 2;
+    }
+    0
+}
+
+fn report(error: &d4t4::Error, input: &str, path: &str) -> i32 {
+    eprint!("{}", error.diagnostic().render(input));
+    eprintln!("in {}", path);
+    2
+}
@@ -0,0 +1,493 @@
+use crate::error::{Error, Span};
+use crate::options::ParseOptions;
+use crate::parser::{parse_with_spans, Parsed, Segment};
+use crate::ser::NUMBER_TOKEN;
+use crate::Result;
+use serde::de::{
+    self, DeserializeOwned, DeserializeSeed, EnumAccess, IntoDeserializer, MapAccess, SeqAccess,
+    Unexpected, VariantAccess, Visitor,
+};
+use serde::forward_to_deserialize_any;
+use serde_json::{Number, Value};
+use std::fmt;
+use std::io;
+use std::str::FromStr;
+
+/// Deserializes a document into `T`, with its references resolved. Errors of the
+/// deserialization point out the value they occurred at.
+///
+/// As a reference can point anywhere in the document, the whole document is parsed into a
+/// value with its references resolved first, and `T` is deserialized from that value. So
+/// `T` can't borrow from the input, and the input is in memory both as text and as value.
+///
+/// ```rust
+///     use serde::Deserialize;
+///
+///     #[derive(Debug, Deserialize)]
+///     struct Server {
+///         host: String,
+///         port: u16,
+///     }
+///
+///     let server: Server = d4t4::from_str("{ host: 'localhost', port: 8080 }").unwrap();
+///     assert_eq!(server.port, 8080);
+///
+///     let error = d4t4::from_str::<Server>("{\n    host: 'localhost',\n}").unwrap_err();
+///     assert_eq!(error.to_string(), "missing field `port` at 1:1");
+/// ```
+pub fn from_str<T: DeserializeOwned>(input: &str) -> Result<T> {
+    from_str_with_options(input, &ParseOptions::default())
+}
+
+pub fn from_str_with_options<T: DeserializeOwned>(
+    input: &str,
+    options: &ParseOptions,
+) -> Result<T> {
+    let parsed = parse_with_spans(input, options)?;
+    T::deserialize(Deserializer {
+        value: &parsed.value,
+        path: Vec::new(),
+        parsed: &parsed,
+    })
+}
+
+/// Reads the whole document and deserializes it like [`from_str`].
+pub fn from_reader<R: io::Read, T: DeserializeOwned>(reader: R) -> Result<T> {
+    from_reader_with_options(reader, &ParseOptions::default())
+}
+
+pub fn from_reader_with_options<R: io::Read, T: DeserializeOwned>(
+    mut reader: R,
+    options: &ParseOptions,
+) -> Result<T> {
+    let mut input = String::new();
+    reader
+        .read_to_string(&mut input)
+        .map_err(|error| Error::Io {
+            path: options
+                .get_path()
+                .map_or_else(|| "input".to_string(), |path| path.display().to_string()),
+            message: error.to_string(),
+            span: Span::default(),
+        })?;
+    from_str_with_options(&input, options)
+}
+
+impl de::Error for Error {
+    fn custom<T: fmt::Display>(message: T) -> Self {
+        Error::Deserialize {
+            message: message.to_string(),
+            span: Span::default(),
+        }
+    }
+}
+
+/// Deserializes a parsed value, `path` being where it is in the document.
+struct Deserializer<'a> {
+    value: &'a Value,
+    path: Vec<Segment>,
+    parsed: &'a Parsed,
+}
+
+impl<'a> Deserializer<'a> {
+    fn child(&self, value: &'a Value, segment: Segment) -> Self {
+        let mut path = self.path.clone();
+        path.push(segment);
+        Self {
+            value,
+            path,
+            parsed: self.parsed,
+        }
+    }
+
+    /// Adds the span of the value to errors that don't have one yet, which are the errors
+    /// of the visitors. Values without a span of their own, e.g. parts of referenced
+    /// values, get the span of the closest value that has one.
+    fn locate(&self, error: Error) -> Error {
+        match error {
+            Error::Deserialize { message, span } if span == Span::default() => {
+                let span = (0..=self.path.len())
+                    .rev()
+                    .find_map(|length| self.parsed.values.get(&self.path[..length]))
+                    .copied()
+                    .unwrap_or_default();
+                Error::Deserialize { message, span }
+            }
+            error => error,
+        }
+    }
+
+    /// Parses a number from its text, which is exact with the `arbitrary_precision` feature.
+    fn parse_number<T: FromStr>(&self) -> Option<T> {
+        match self.value {
+            Value::Number(number) => number.to_string().parse().ok(),
+            _ => None,
+        }
+    }
+
+    fn invalid_type(&self, expected: &dyn de::Expected) -> Error {
+        let unexpected = match self.value {
+            Value::Null => Unexpected::Unit,
+            Value::Bool(bool) => Unexpected::Bool(*bool),
+            Value::Number(number) => get_unexpected_number(number),
+            Value::String(string) => Unexpected::Str(string),
+            Value::Array(_) => Unexpected::Seq,
+            Value::Object(_) => Unexpected::Map,
+        };
+        self.locate(de::Error::invalid_type(unexpected, expected))
+    }
+}
+
+fn get_unexpected_number(number: &Number) -> Unexpected<'_> {
+    if let Some(number) = number.as_u64() {
+        Unexpected::Unsigned(number)
+    } else if let Some(number) = number.as_i64() {
+        Unexpected::Signed(number)
+    } else if let Some(number) = number.as_f64() {
+        Unexpected::Float(number)
+    } else {
+        Unexpected::Other("number beyond f64")
+    }
+}
+
+/// Whether the number is written the way its `f64` is, which is always the case without the
+/// `arbitrary_precision` feature.
+fn is_exact_float(number: &Number) -> bool {
+    if !cfg!(feature = "arbitrary_precision") {
+        return true;
+    }
+    let text = number.to_string();
+    match number.as_f64() {
+        Some(float) if float.is_finite() => {
+            ryu::Buffer::new().format_finite(float) == text || float.to_string() == text
+        }
+        _ => false,
+    }
+}
+
+/// A number as the map `serde_json` uses for numbers of arbitrary precision, holding its text
+/// under a private key.
+struct NumberText {
+    text: Option<String>,
+}
+
+impl<'de> MapAccess<'de> for NumberText {
+    type Error = Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>> {
+        if self.text.is_none() {
+            return Ok(None);
+        }
+        seed.deserialize(NUMBER_TOKEN.into_deserializer()).map(Some)
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value> {
+        let text = self.text.take().expect("value is taken after its key");
+        seed.deserialize(text.into_deserializer())
+    }
+}
+
+impl<'de, 'a> de::Deserializer<'de> for Deserializer<'a> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        let value = match self.value {
+            Value::Null => visitor.visit_unit(),
+            Value::Bool(bool) => visitor.visit_bool(*bool),
+            Value::Number(number) => {
+                if let Some(number) = number.as_u64() {
+                    visitor.visit_u64(number)
+                } else if let Some(number) = number.as_i64() {
+                    visitor.visit_i64(number)
+                } else if let Some(float) = number.as_f64().filter(|_| is_exact_float(number)) {
+                    visitor.visit_f64(float)
+                } else {
+                    // Passed on the way `serde_json` does, so `Number` and `Value` keep it exactly
+                    visitor.visit_map(NumberText {
+                        text: Some(number.to_string()),
+                    })
+                }
+            }
+            Value::String(string) => visitor.visit_str(string),
+            Value::Array(array) => visitor.visit_seq(Seq {
+                deserializer: &self,
+                array,
+                index: 0,
+            }),
+            Value::Object(object) => visitor.visit_map(Object {
+                deserializer: &self,
+                iter: object.iter(),
+                value: None,
+            }),
+        };
+        value.map_err(|error| self.locate(error))
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self.value {
+            Value::Null => visitor.visit_none(),
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        // Either a unit variant as a string or an object with the variant as its only key
+        let (variant, value) = match self.value {
+            Value::String(variant) => (variant, None),
+            Value::Object(object) if object.len() == 1 => {
+                let (variant, value) = object.iter().next().expect("object has one key");
+                (variant, Some(value))
+            }
+            _ => return Err(self.invalid_type(&"a string or an object with a single key")),
+        };
+        let value = visitor.visit_enum(Enum {
+            deserializer: &self,
+            variant,
+            value,
+        });
+        value.map_err(|error| self.locate(error))
+    }
+
+    fn deserialize_i128<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self.parse_number() {
+            Some(number) => visitor
+                .visit_i128(number)
+                .map_err(|error| self.locate(error)),
+            None => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_u128<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self.parse_number() {
+            Some(number) => visitor
+                .visit_u128(number)
+                .map_err(|error| self.locate(error)),
+            None => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_f32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.deserialize_f64(visitor)
+    }
+
+    fn deserialize_f64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self.value {
+            Value::Number(number) => match number.as_f64().filter(|float| float.is_finite()) {
+                Some(float) => visitor.visit_f64(float),
+                // Only numbers of `arbitrary_precision` can be beyond the range of f64
+                None => Err(de::Error::invalid_value(
+                    Unexpected::Other(&format!("number {}", number)),
+                    &visitor,
+                )),
+            }
+            .map_err(|error| self.locate(error)),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 u8 u16 u32 u64 char str string bytes byte_buf
+        unit unit_struct seq tuple tuple_struct map struct identifier ignored_any
+    }
+}
+
+struct Seq<'a, 'b> {
+    deserializer: &'b Deserializer<'a>,
+    array: &'a [Value],
+    index: usize,
+}
+
+impl<'de, 'a, 'b> SeqAccess<'de> for Seq<'a, 'b> {
+    type Error = Error;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(&mut self, seed: T) -> Result<Option<T::Value>> {
+        let value = match self.array.get(self.index) {
+            Some(value) => value,
+            None => return Ok(None),
+        };
+        let segment = Segment::Index(self.index as i64);
+        self.index += 1;
+        seed.deserialize(self.deserializer.child(value, segment))
+            .map(Some)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.array.len() - self.index)
+    }
+}
+
+struct Object<'a, 'b> {
+    deserializer: &'b Deserializer<'a>,
+    iter: serde_json::map::Iter<'a>,
+    value: Option<(&'a String, &'a Value)>,
+}
+
+impl<'de, 'a, 'b> MapAccess<'de> for Object<'a, 'b> {
+    type Error = Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>> {
+        let (key, value) = match self.iter.next() {
+            Some(entry) => entry,
+            None => return Ok(None),
+        };
+        self.value = Some((key, value));
+        seed.deserialize(KeyDeserializer(key))
+            .map(Some)
+            .map_err(|error| match error {
+                // Unknown fields etc. are pointed out at their key
+                Error::Deserialize { message, .. } => {
+                    let mut path = self.deserializer.path.clone();
+                    path.push(Segment::Key(key.clone()));
+                    let span = self.deserializer.parsed.keys.get(&path).copied();
+                    Error::Deserialize {
+                        message,
+                        span: span.unwrap_or_default(),
+                    }
+                }
+                error => error,
+            })
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value> {
+        let (key, value) = self.value.take().expect("value is read after its key");
+        seed.deserialize(self.deserializer.child(value, Segment::Key(key.clone())))
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.iter.len())
+    }
+}
+
+/// Deserializes keys, which may be read as numbers or booleans like in JSON, e.g. for a
+/// `HashMap<u32, String>`.
+struct KeyDeserializer<'a>(&'a str);
+
+macro_rules! deserialize_key_from_str {
+    ($($method:ident => $visit:ident),*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+                match self.0.parse() {
+                    Ok(value) => visitor.$visit(value),
+                    Err(_) => Err(de::Error::invalid_value(Unexpected::Str(self.0), &visitor)),
+                }
+            }
+        )*
+    };
+}
+
+impl<'de, 'a> de::Deserializer<'de> for KeyDeserializer<'a> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_str(self.0)
+    }
+
+    deserialize_key_from_str! {
+        deserialize_bool => visit_bool,
+        deserialize_i8 => visit_i8,
+        deserialize_i16 => visit_i16,
+        deserialize_i32 => visit_i32,
+        deserialize_i64 => visit_i64,
+        deserialize_i128 => visit_i128,
+        deserialize_u8 => visit_u8,
+        deserialize_u16 => visit_u16,
+        deserialize_u32 => visit_u32,
+        deserialize_u64 => visit_u64,
+        deserialize_u128 => visit_u128
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        let deserializer: de::value::StrDeserializer<Error> = self.0.into_deserializer();
+        deserializer.deserialize_enum(name, variants, visitor)
+    }
+
+    forward_to_deserialize_any! {
+        f32 f64 char str string bytes byte_buf option unit unit_struct seq tuple tuple_struct
+        map struct identifier ignored_any
+    }
+}
+
+struct Enum<'a, 'b> {
+    deserializer: &'b Deserializer<'a>,
+    variant: &'a String,
+    value: Option<&'a Value>,
+}
+
+impl<'de, 'a, 'b> EnumAccess<'de> for Enum<'a, 'b> {
+    type Error = Error;
+    type Variant = Self;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(self, seed: V) -> Result<(V::Value, Self)> {
+        let deserializer: de::value::StrDeserializer<Error> =
+            self.variant.as_str().into_deserializer();
+        let variant = seed.deserialize(deserializer)?;
+        Ok((variant, self))
+    }
+}
+
+impl<'de, 'a, 'b> VariantAccess<'de> for Enum<'a, 'b> {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<()> {
+        match self.value {
+            None | Some(Value::Null) => Ok(()),
+            Some(_) => Err(self.deserializer.invalid_type(&"a unit variant")),
+        }
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value> {
+        seed.deserialize(self.get_value(&"a newtype variant")?)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value> {
+        de::Deserializer::deserialize_seq(self.get_value(&"a tuple variant")?, visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        de::Deserializer::deserialize_map(self.get_value(&"a struct variant")?, visitor)
+    }
+}
+
+impl<'a, 'b> Enum<'a, 'b> {
+    /// The deserializer of the value of a variant that isn't a unit variant.
+    fn get_value(&self, expected: &dyn de::Expected) -> Result<Deserializer<'a>> {
+        match self.value {
+            Some(value) => Ok(self
+                .deserializer
+                .child(value, Segment::Key(self.variant.clone()))),
+            None => Err(self
+                .deserializer
+                .locate(de::Error::invalid_type(Unexpected::UnitVariant, expected))),
+        }
+    }
+}
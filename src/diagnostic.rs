@@ -0,0 +1,339 @@
+use crate::error::{Error, Span};
+use std::fmt::Write;
+
+/// A renderable description of an error, pointing out where in the source it occurred.
+///
+/// ```rust
+/// let input = "{ color: { red: '#f00' }, border: '&{color.rde}' }";
+/// let error = d4t4::parse(input).unwrap_err();
+/// let diagnostic = error.diagnostic();
+/// assert_eq!(diagnostic.help.as_deref(), Some("did you mean `color.red`?"));
+/// println!("{}", diagnostic.render(input));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub message: String,
+    /// The first label is the primary one, the rest point out related locations.
+    pub labels: Vec<Label>,
+    pub help: Option<String>,
+    pub notes: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Label {
+    pub span: Span,
+    pub message: String,
+    pub primary: bool,
+}
+
+impl Label {
+    fn primary(span: Span, message: impl Into<String>) -> Self {
+        Self {
+            span,
+            message: message.into(),
+            primary: true,
+        }
+    }
+
+    fn secondary(span: Span, message: impl Into<String>) -> Self {
+        Self {
+            span,
+            message: message.into(),
+            primary: false,
+        }
+    }
+}
+
+impl Diagnostic {
+    /// Renders the diagnostic as plain text, `source` being the input that was parsed.
+    pub fn render(&self, source: &str) -> String {
+        self.render_with_style(source, &Style::PLAIN)
+    }
+
+    /// Renders the diagnostic with ANSI colors, for printing to a terminal.
+    pub fn render_ansi(&self, source: &str) -> String {
+        self.render_with_style(source, &Style::ANSI)
+    }
+
+    fn render_with_style(&self, source: &str, style: &Style) -> String {
+        let mut output = String::new();
+        let _ = self.write(&mut output, source, style);
+        output
+    }
+
+    fn write(&self, f: &mut String, source: &str, style: &Style) -> std::fmt::Result {
+        writeln!(
+            f,
+            "{}error{}{}: {}{}",
+            style.error, style.reset, style.bold, self.message, style.reset
+        )?;
+        let mut labels = self
+            .labels
+            .iter()
+            .filter(|label| label.span.line > 0)
+            .collect::<Vec<_>>();
+        labels.sort_by_key(|label| (label.span.line, label.span.column));
+        let last_line = labels.iter().map(|label| label.span.line).max();
+        let width = last_line.unwrap_or(0).to_string().len();
+        let gutter = format!("{}{} |{}", style.gutter, " ".repeat(width), style.reset);
+
+        if let Some(primary) = self.labels.first().filter(|label| label.span.line > 0) {
+            writeln!(
+                f,
+                "{}{}-->{} {}",
+                " ".repeat(width),
+                style.gutter,
+                style.reset,
+                primary.span
+            )?;
+            writeln!(f, "{}", gutter)?;
+        }
+        let mut previous_line = None;
+        for label in &labels {
+            let line = label.span.line;
+            if previous_line != Some(line) {
+                if matches!(previous_line, Some(previous) if previous + 1 < line) {
+                    writeln!(f, "{}...{}", style.gutter, style.reset)?;
+                }
+                let text = source.lines().nth(line - 1).unwrap_or("");
+                writeln!(
+                    f,
+                    "{}{:>width$} |{} {}",
+                    style.gutter,
+                    line,
+                    style.reset,
+                    expand_tabs(text),
+                    width = width
+                )?;
+                previous_line = Some(line);
+            }
+            let text = source.lines().nth(line - 1).unwrap_or("");
+            let (start, length) = get_underline(text, source, label.span);
+            let (color, marker) = if label.primary {
+                (style.error, "^")
+            } else {
+                (style.gutter, "-")
+            };
+            let message = if label.message.is_empty() {
+                String::new()
+            } else {
+                format!(" {}", label.message)
+            };
+            writeln!(
+                f,
+                "{} {}{}{}{}",
+                gutter,
+                " ".repeat(start),
+                color,
+                marker.repeat(length) + &message,
+                style.reset
+            )?;
+        }
+        if !labels.is_empty() && (self.help.is_some() || !self.notes.is_empty()) {
+            writeln!(f, "{}", gutter)?;
+        }
+        for note in &self.notes {
+            writeln!(
+                f,
+                "{}{} ={} {}note{}: {}",
+                " ".repeat(width),
+                style.gutter,
+                style.reset,
+                style.bold,
+                style.reset,
+                note
+            )?;
+        }
+        if let Some(help) = &self.help {
+            writeln!(
+                f,
+                "{}{} ={} {}help{}: {}",
+                " ".repeat(width),
+                style.gutter,
+                style.reset,
+                style.bold,
+                style.reset,
+                help
+            )?;
+        }
+        Ok(())
+    }
+}
+
+struct Style {
+    error: &'static str,
+    gutter: &'static str,
+    bold: &'static str,
+    reset: &'static str,
+}
+
+impl Style {
+    const PLAIN: Style = Style {
+        error: "",
+        gutter: "",
+        bold: "",
+        reset: "",
+    };
+    const ANSI: Style = Style {
+        error: "\x1b[1;31m",
+        gutter: "\x1b[1;34m",
+        bold: "\x1b[1m",
+        reset: "\x1b[0m",
+    };
+}
+
+const TAB_WIDTH: usize = 4;
+
+fn expand_tabs(text: &str) -> String {
+    text.replace('\t', &" ".repeat(TAB_WIDTH))
+}
+
+fn char_width(c: char) -> usize {
+    if c == '\t' {
+        TAB_WIDTH
+    } else {
+        1
+    }
+}
+
+/// Returns where the underline of a span starts on its first line and how long it is. Spans
+/// that continue on the next lines are underlined to the end of the first line.
+fn get_underline(line: &str, source: &str, span: Span) -> (usize, usize) {
+    let before = line.chars().take(span.column.saturating_sub(1));
+    let start = before.map(char_width).sum();
+    let spanned = source.get(span.start..span.end).unwrap_or("");
+    let length = spanned
+        .lines()
+        .next()
+        .unwrap_or("")
+        .chars()
+        .map(char_width)
+        .sum();
+    (start, std::cmp::max(length, 1))
+}
+
+impl Error {
+    /// Describes every error for rendering, one diagnostic for each of [`Error::errors`].
+    pub fn diagnostics(&self) -> Vec<Diagnostic> {
+        self.errors().iter().map(Error::diagnostic).collect()
+    }
+
+    /// Describes the error for rendering, see [`Diagnostic::render`]. Only the first error of
+    /// [`Error::Multiple`] is described, see [`Error::diagnostics`] for all of them.
+    pub fn diagnostic(&self) -> Diagnostic {
+        if let Error::Multiple { errors } = self {
+            let mut diagnostic = errors[0].diagnostic();
+            diagnostic
+                .notes
+                .push(format!("{} more errors were found", errors.len() - 1));
+            return diagnostic;
+        }
+        let mut diagnostic = Diagnostic {
+            message: String::new(),
+            labels: Vec::new(),
+            help: None,
+            notes: Vec::new(),
+        };
+        let (message, label) = match self {
+            Error::Syntax { message, .. } => (message.clone(), ""),
+            Error::Extension { extension, .. } => {
+                diagnostic.help = Some(format!(
+                    "allow it with `ParseOptions::{}(true)`",
+                    extension.option_name()
+                ));
+                (format!("Unsupported {}", extension), "not allowed")
+            }
+            Error::DuplicateKey { key, .. } => (
+                format!("Object already contains key: {}", key),
+                "duplicate key",
+            ),
+            Error::InvalidEscape { escape, .. } => (format!("Invalid escape {}", escape), ""),
+            Error::InvalidNumber { number, .. } => (format!("Invalid number {}", number), ""),
+            Error::InvalidReference { message, .. } => (message.clone(), ""),
+            Error::UnresolvedReference {
+                target,
+                missing,
+                parent,
+                suggestion,
+                ..
+            } => {
+                if let Some(parent) = parent {
+                    let message = format!("has no `{}`", missing);
+                    diagnostic.labels.push(Label::secondary(**parent, message));
+                }
+                diagnostic.help = suggestion
+                    .as_ref()
+                    .map(|suggestion| format!("did you mean `{}`?", suggestion));
+                let message = format!("Unresolved reference {}, {} was not found", target, missing);
+                (message, "unresolved reference")
+            }
+            Error::CyclicReference { cycle, .. } => (
+                format!("Cyclic reference: {}", cycle.join(" -> ")),
+                "part of the cycle",
+            ),
+            Error::CyclicImport { cycle, .. } => (
+                format!("Cyclic import: {}", cycle.join(" -> ")),
+                "part of the cycle",
+            ),
+            Error::Io { path, message, .. } => {
+                (format!("Failed to read {}: {}", path, message), "")
+            }
+            Error::Import { path, source, .. } => {
+                let inner = source.diagnostic();
+                diagnostic
+                    .notes
+                    .push(format!("the error is at {} in {}", source.span(), path));
+                diagnostic.notes.extend(inner.notes);
+                diagnostic.help = inner.help;
+                (format!("{} in {}", inner.message, path), "imported here")
+            }
+            Error::Deserialize { message, .. } => (message.clone(), ""),
+            Error::Serialize { message } => (message.clone(), ""),
+            Error::Edit { path, message } => (format!("Cannot edit {}: {}", path, message), ""),
+            Error::Multiple { .. } => unreachable!("multiple errors are described separately"),
+        };
+        diagnostic.message = message;
+        // Errors that aren't about the source, e.g. of writing a value, have no span
+        if self.span() != Span::default() {
+            diagnostic
+                .labels
+                .insert(0, Label::primary(self.span(), label));
+        }
+        diagnostic
+    }
+}
+
+/// Finds the candidate that is most similar to `name`, as long as it is similar enough to be
+/// a likely typo.
+pub(crate) fn find_similar<'a>(
+    name: &str,
+    candidates: impl Iterator<Item = &'a str>,
+) -> Option<&'a str> {
+    let max_distance = std::cmp::max(1, name.chars().count() / 3);
+    candidates
+        .map(|candidate| (get_edit_distance(name, candidate), candidate))
+        .filter(|(distance, _)| *distance <= max_distance)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate)
+}
+
+/// The Levenshtein distance, where swapping two adjacent characters counts as one edit.
+fn get_edit_distance(a: &str, b: &str) -> usize {
+    let a = a.chars().collect::<Vec<_>>();
+    let b = b.chars().collect::<Vec<_>>();
+    let mut rows = vec![(0..=b.len()).collect::<Vec<_>>()];
+    for i in 1..=a.len() {
+        let mut row = vec![i; b.len() + 1];
+        for j in 1..=b.len() {
+            let cost = if a[i - 1] == b[j - 1] { 0 } else { 1 };
+            row[j] = (rows[i - 1][j] + 1)
+                .min(row[j - 1] + 1)
+                .min(rows[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                row[j] = row[j].min(rows[i - 2][j - 2] + 1);
+            }
+        }
+        rows.push(row);
+    }
+    rows[a.len()][b.len()]
+}
@@ -0,0 +1,490 @@
+use super::{is_value, Document, Element, Node, NodeKind, Token, TokenKind};
+use crate::error::{Error, Span};
+use crate::options::ParseOptions;
+use crate::parser::{parse_key, Segment};
+use crate::ser::{is_identifier, write_string};
+use crate::Result;
+use serde_json::Value;
+
+/// Edits change only the tokens of the edited value, everything around them is kept as it
+/// is written. Paths are written like references, e.g. `colors[0].name`, and an empty path
+/// is the root value.
+///
+/// ```rust
+///     use d4t4::Document;
+///
+///     let mut document = Document::parse("{\n    // red\n    red: '#fed7d7',\n}").unwrap();
+///     document.set("red", "#f00").unwrap();
+///     document.insert("green", "#0f0").unwrap();
+///     assert_eq!(document.to_string(), "{\n    // red\n    red: '#f00',\n    green: '#0f0',\n}");
+/// ```
+impl Document {
+    /// Returns the value at the path.
+    pub fn get(&self, path: &str) -> Option<&Element> {
+        let path = parse_path(path).ok()?;
+        let mut value = self.root.value()?;
+        for segment in &path {
+            let node = match value {
+                Element::Node(node) => node,
+                Element::Token(_) => return None,
+            };
+            let index = find_item(node, segment)?;
+            value = get_item_value(&node.children[index]);
+        }
+        Some(value)
+    }
+
+    /// Replaces the value at the path. A key that doesn't exist yet is added to its object.
+    /// Strings are written as plain text, `&{` is escaped so that it isn't read as a
+    /// reference.
+    pub fn set<T: Into<Value>>(&mut self, path: &str, value: T) -> Result<()> {
+        let value = value.into();
+        let quote = self.get_quote();
+        let (parent, last) = match self.split_path(path)? {
+            (_, None) => {
+                let index = get_value_index(&self.root).expect("document should have a value");
+                self.root.children[index] = parse_element(&write_value(&value, quote));
+                return Ok(());
+            }
+            (parent, Some(last)) => (parent, last),
+        };
+        let container = self.get_container_mut(path, &parent)?;
+        match find_item(container, &last) {
+            Some(index) => {
+                let element = parse_element(&write_value(&value, quote));
+                match &mut container.children[index] {
+                    Element::Node(member) if member.kind == NodeKind::Member => {
+                        let index = get_value_index(member).expect("member should have a value");
+                        member.children[index] = element;
+                    }
+                    item => *item = element,
+                }
+                Ok(())
+            }
+            None => insert_item(container, path, &last, &value, quote),
+        }
+    }
+
+    /// Adds a key to an object or a value to an array. The value is inserted in front of
+    /// the index of the path, an index right after the last value appends to the array.
+    pub fn insert<T: Into<Value>>(&mut self, path: &str, value: T) -> Result<()> {
+        let value = value.into();
+        let quote = self.get_quote();
+        let (parent, last) = self.split_last(path)?;
+        let container = self.get_container_mut(path, &parent)?;
+        insert_item(container, path, &last, &value, quote)
+    }
+
+    /// Removes a key from an object or a value from an array, together with its comma.
+    /// Comments around the value are kept.
+    pub fn remove(&mut self, path: &str) -> Result<()> {
+        let (parent, last) = self.split_last(path)?;
+        let container = self.get_container_mut(path, &parent)?;
+        let index = find_item(container, &last).ok_or_else(|| get_error(path, "not found"))?;
+        let children = &mut container.children;
+        let next = (index + 1..children.len()).find(|x| !children[*x].is_trivia());
+        let previous = (0..index).rev().find(|x| !children[*x].is_trivia());
+        let comma = match (next, previous) {
+            (Some(next), _) if is_token(&children[next], TokenKind::Comma) => Some(next),
+            (_, Some(previous)) if is_token(&children[previous], TokenKind::Comma) => {
+                Some(previous)
+            }
+            _ => None,
+        };
+        // The whitespace in front of the next value, or of this one when it is the last
+        let whitespace = match comma {
+            Some(comma) if comma > index => comma + 1,
+            _ => index.wrapping_sub(1),
+        };
+        let mut removed = vec![index];
+        removed.extend(comma);
+        if matches!(children.get(whitespace), Some(element) if is_token(element, TokenKind::Whitespace))
+        {
+            // The newline that ends a line comment is kept, or what follows would be commented
+            let after_comment = whitespace
+                .checked_sub(1)
+                .is_some_and(|x| is_token(&children[x], TokenKind::LineComment));
+            match &mut children[whitespace] {
+                Element::Token(token) if after_comment && token.text.contains('\n') => {
+                    let end = token.text.rfind('\n').expect("checked to hold a newline");
+                    token.text.truncate(end + 1);
+                }
+                _ => removed.push(whitespace),
+            }
+        }
+        removed.sort_unstable();
+        for index in removed.into_iter().rev() {
+            children.remove(index);
+        }
+        Ok(())
+    }
+
+    /// Renames a key of an object, keeping its quotes. Renaming a key to its own name leaves
+    /// it as it is written.
+    pub fn rename_key(&mut self, path: &str, key: &str) -> Result<()> {
+        let quote = self.get_quote();
+        let (parent, last) = self.split_last(path)?;
+        let container = self.get_container_mut(path, &parent)?;
+        if container.kind != NodeKind::Object {
+            return Err(get_error(path, "only keys of objects can be renamed"));
+        }
+        let index = find_item(container, &last).ok_or_else(|| get_error(path, "not found"))?;
+        if matches!(&last, Segment::Key(name) if name == key) {
+            return Ok(());
+        }
+        if find_item(container, &Segment::Key(key.to_string())).is_some() {
+            return Err(get_error(
+                path,
+                &format!("the object already contains {}", key),
+            ));
+        }
+        let member = match &mut container.children[index] {
+            Element::Node(member) => member,
+            Element::Token(_) => unreachable!("objects only hold members"),
+        };
+        let old = member.key().expect("member should have a key");
+        let text = match (old.kind, old.text.chars().next()) {
+            (TokenKind::Identifier, _) if is_identifier(key) => key.to_string(),
+            (TokenKind::String, Some(quote)) => write_string(key, quote),
+            _ => write_string(key, quote),
+        };
+        let kind = if is_identifier(&text) {
+            TokenKind::Identifier
+        } else {
+            TokenKind::String
+        };
+        member.children[0] = Element::Token(Token {
+            kind,
+            text,
+            span: old.span,
+        });
+        Ok(())
+    }
+
+    fn split_path(&self, path: &str) -> Result<(Vec<Segment>, Option<Segment>)> {
+        let mut segments = parse_path(path)?;
+        let last = segments.pop();
+        Ok((segments, last))
+    }
+
+    fn split_last(&self, path: &str) -> Result<(Vec<Segment>, Segment)> {
+        match self.split_path(path)? {
+            (parent, Some(last)) => Ok((parent, last)),
+            (_, None) => Err(get_error(path, "the root value has no key or index")),
+        }
+    }
+
+    /// Returns the object or array at the path.
+    fn get_container_mut(&mut self, path: &str, segments: &[Segment]) -> Result<&mut Node> {
+        let index = get_value_index(&self.root).expect("document should have a value");
+        let mut value = &mut self.root.children[index];
+        for segment in segments {
+            let node = match value {
+                Element::Node(node) => node,
+                Element::Token(_) => return Err(get_error(path, "not an object or array")),
+            };
+            let index = find_item(node, segment).ok_or_else(|| get_error(path, "not found"))?;
+            value = get_item_value_mut(&mut node.children[index]);
+        }
+        match value {
+            Element::Node(node) => Ok(node),
+            Element::Token(_) => Err(get_error(path, "not an object or array")),
+        }
+    }
+
+    /// The quote of the first string in the document, so that new strings look the same.
+    fn get_quote(&self) -> char {
+        self.root
+            .tokens()
+            .into_iter()
+            .find(|token| token.kind == TokenKind::String)
+            .and_then(|token| token.text.chars().next())
+            .unwrap_or('"')
+    }
+}
+
+fn get_error(path: &str, message: &str) -> Error {
+    Error::Edit {
+        path: path.to_string(),
+        message: message.to_string(),
+    }
+}
+
+/// Parses a path like `a.b[0]`. Unlike in references there are no scopes and no fallbacks.
+fn parse_path(path: &str) -> Result<Vec<Segment>> {
+    let mut segments = Vec::new();
+    if path.is_empty() {
+        return Ok(segments);
+    }
+    for part in path.split('.') {
+        let (key, mut indices) = match part.find('[') {
+            Some(index) => part.split_at(index),
+            None => (part, ""),
+        };
+        if !key.is_empty() {
+            segments.push(Segment::Key(key.to_string()));
+        }
+        while !indices.is_empty() {
+            let end = indices
+                .find(']')
+                .ok_or_else(|| get_error(path, "missing ]"))?;
+            let index = indices[1..end]
+                .parse()
+                .map_err(|_| get_error(path, "invalid index"))?;
+            segments.push(Segment::Index(index));
+            indices = &indices[end + 1..];
+            if !indices.is_empty() && !indices.starts_with('[') {
+                return Err(get_error(path, "expected ["));
+            }
+        }
+        if key.is_empty() && part.is_empty() {
+            return Err(get_error(path, "empty key"));
+        }
+    }
+    Ok(segments)
+}
+
+/// Returns the index of the member or array value that the segment points to.
+fn find_item(node: &Node, segment: &Segment) -> Option<usize> {
+    match (node.kind, segment) {
+        (NodeKind::Object, Segment::Key(key)) => node.children.iter().position(|child| {
+            matches!(child, Element::Node(member) if member.kind == NodeKind::Member
+                && member.key().map(get_key_name).as_deref() == Some(key.as_str()))
+        }),
+        (NodeKind::Array, Segment::Index(index)) => {
+            let items = get_items(node);
+            let index = if *index < 0 {
+                items.len() as i64 + index
+            } else {
+                *index
+            };
+            if index < 0 {
+                None
+            } else {
+                items.get(index as usize).copied()
+            }
+        }
+        (NodeKind::Array, Segment::Key(key)) => find_item(node, &Segment::Index(key.parse().ok()?)),
+        _ => None,
+    }
+}
+
+/// The indices of the members of an object or the values of an array.
+fn get_items(node: &Node) -> Vec<usize> {
+    (0..node.children.len())
+        .filter(|index| match &node.children[*index] {
+            Element::Node(_) => true,
+            Element::Token(token) => is_value(token.kind),
+        })
+        .collect()
+}
+
+fn get_item_value(item: &Element) -> &Element {
+    match item {
+        Element::Node(member) if member.kind == NodeKind::Member => {
+            member.value().expect("member should have a value")
+        }
+        item => item,
+    }
+}
+
+fn get_item_value_mut(item: &mut Element) -> &mut Element {
+    let index = match item {
+        Element::Node(member) if member.kind == NodeKind::Member => {
+            get_value_index(member).expect("member should have a value")
+        }
+        _ => return item,
+    };
+    match item {
+        Element::Node(member) => &mut member.children[index],
+        Element::Token(_) => unreachable!("checked to be a member"),
+    }
+}
+
+fn get_value_index(node: &Node) -> Option<usize> {
+    let value = node.value()? as *const Element;
+    node.children
+        .iter()
+        .position(|child| std::ptr::eq(child, value))
+}
+
+fn get_key_name(token: &Token) -> String {
+    parse_key(&token.text, &ParseOptions::default()).unwrap_or_else(|_| token.text.clone())
+}
+
+fn is_token(element: &Element, kind: TokenKind) -> bool {
+    matches!(element, Element::Token(token) if token.kind == kind)
+}
+
+/// Adds a member to an object or a value to an array, following the layout of the values
+/// that are already there.
+fn insert_item(
+    container: &mut Node,
+    path: &str,
+    segment: &Segment,
+    value: &Value,
+    quote: char,
+) -> Result<()> {
+    let items = get_items(container);
+    let (item, position) = match (container.kind, segment) {
+        (NodeKind::Object, Segment::Key(key)) => {
+            if find_item(container, segment).is_some() {
+                return Err(get_error(path, "the key already exists"));
+            }
+            let uses_identifiers = container
+                .members()
+                .all(|member| member.key().map(|key| key.kind) == Some(TokenKind::Identifier));
+            let key = if uses_identifiers && !items.is_empty() && is_identifier(key) {
+                key.clone()
+            } else {
+                write_string(key, quote)
+            };
+            let object = parse_element(&format!("{{{}: {}}}", key, write_value(value, quote)));
+            let member = match object {
+                Element::Node(mut object) => object
+                    .children
+                    .drain(..)
+                    .find(|child| matches!(child, Element::Node(_)))
+                    .expect("object should have a member"),
+                Element::Token(_) => unreachable!("an object was written"),
+            };
+            (member, items.len())
+        }
+        (NodeKind::Array, Segment::Index(index)) => {
+            let index = if *index < 0 {
+                items.len() as i64 + index + 1
+            } else {
+                *index
+            };
+            if index < 0 || index as usize > items.len() {
+                return Err(get_error(path, "index out of bounds"));
+            }
+            (parse_element(&write_value(value, quote)), index as usize)
+        }
+        _ => return Err(get_error(path, "not an object or array")),
+    };
+
+    let children = &mut container.children;
+    if let Some(&next) = items.get(position) {
+        // In front of another value, with the same whitespace in front of both
+        let whitespace = match &children[next - 1] {
+            Element::Token(token) if token.kind == TokenKind::Whitespace => token.text.clone(),
+            _ => " ".to_string(),
+        };
+        let inserted = vec![
+            item,
+            new_token(TokenKind::Comma, ","),
+            new_whitespace(&whitespace),
+        ];
+        children.splice(next..next, inserted);
+        return Ok(());
+    }
+    let last = match items.last() {
+        Some(&last) => last,
+        None => {
+            // The only value of an empty object or array, replacing the whitespace between
+            // its brackets while comments are kept after it
+            let inserted = vec![new_whitespace(" "), item, new_whitespace(" ")];
+            let end = children.len() - 1;
+            let blank = children[1..end]
+                .iter()
+                .all(|child| is_token(child, TokenKind::Whitespace));
+            if blank {
+                children.splice(1..end, inserted);
+            } else {
+                children.splice(1..1, inserted);
+            }
+            return Ok(());
+        }
+    };
+    // After the last value, on a new line if the values are on separate lines
+    let whitespace = match &children[last - 1] {
+        Element::Token(token) if token.kind == TokenKind::Whitespace => token.text.clone(),
+        _ => " ".to_string(),
+    };
+    let whitespace = match whitespace.rfind('\n') {
+        Some(index) => whitespace[index..].to_string(),
+        None => " ".to_string(),
+    };
+    let comma = (last + 1..children.len())
+        .find(|x| !children[*x].is_trivia())
+        .filter(|x| is_token(&children[*x], TokenKind::Comma));
+    let (at, mut inserted) = match comma {
+        // A trailing comma stays a trailing comma
+        Some(comma) => (
+            comma + 1,
+            vec![
+                new_whitespace(&whitespace),
+                item,
+                new_token(TokenKind::Comma, ","),
+            ],
+        ),
+        None => {
+            children.insert(last + 1, new_token(TokenKind::Comma, ","));
+            (last + 2, vec![new_whitespace(&whitespace), item])
+        }
+    };
+    // A comment on the line of the last value stays on that line, with the new value on
+    // the next one
+    let comment = (at..children.len())
+        .take_while(|x| match &children[*x] {
+            Element::Token(token) => match token.kind {
+                TokenKind::Whitespace => !token.text.contains('\n'),
+                kind => kind.is_trivia(),
+            },
+            Element::Node(_) => false,
+        })
+        .find(|x| is_token(&children[*x], TokenKind::LineComment));
+    let at = match comment {
+        Some(comment) => {
+            if !whitespace.contains('\n') {
+                inserted[0] = new_whitespace("\n");
+            }
+            comment + 1
+        }
+        None => at,
+    };
+    children.splice(at..at, inserted);
+    Ok(())
+}
+
+fn new_token(kind: TokenKind, text: &str) -> Element {
+    Element::Token(Token {
+        kind,
+        text: text.to_string(),
+        span: Span::default(),
+    })
+}
+
+fn new_whitespace(text: &str) -> Element {
+    new_token(TokenKind::Whitespace, text)
+}
+
+/// Parses a written value into an element, the spans of new tokens are meaningless.
+fn parse_element(text: &str) -> Element {
+    let document = Document::parse(text).expect("written values should be valid");
+    let mut root = document.root;
+    let index = get_value_index(&root).expect("document should have a value");
+    root.children.swap_remove(index)
+}
+
+fn write_value(value: &Value, quote: char) -> String {
+    match value {
+        Value::String(string) => write_string(string, quote),
+        Value::Array(array) => {
+            let values = array.iter().map(|value| write_value(value, quote));
+            format!("[{}]", values.collect::<Vec<_>>().join(", "))
+        }
+        Value::Object(object) => {
+            let members = object.iter().map(|(key, value)| {
+                format!(
+                    "{}: {}",
+                    write_string(key, quote),
+                    write_value(value, quote)
+                )
+            });
+            format!("{{{}}}", members.collect::<Vec<_>>().join(", "))
+        }
+        _ => value.to_string(),
+    }
+}
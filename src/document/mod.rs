@@ -0,0 +1,358 @@
+mod edit;
+
+use crate::error::Span;
+use crate::options::ParseOptions;
+use crate::tokenizer::{Rule, Tokenizer};
+use crate::Result;
+use pest::{iterators::Pair, Parser};
+use serde_json::Value;
+use std::fmt;
+use std::str::FromStr;
+
+/// A parsed document that keeps every token, including whitespace and comments, so that
+/// writing it back gives the exact same text.
+///
+/// ```rust
+///     use d4t4::Document;
+///
+///     let input = "{\n    // the best color\n    red: '#f00', \n}";
+///     let document = Document::parse(input).unwrap();
+///     assert_eq!(document.to_string(), input);
+///     assert_eq!(document.to_value().unwrap()["red"], "#f00");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Document {
+    root: Node,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NodeKind {
+    /// The root value and the whitespace and comments around it.
+    Document,
+    Object,
+    /// A key and value of an object, including the colon and anything between them.
+    Member,
+    Array,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenKind {
+    Whitespace,
+    LineComment,
+    BlockComment,
+    OpenBrace,
+    CloseBrace,
+    OpenBracket,
+    CloseBracket,
+    Comma,
+    Colon,
+    /// An unquoted key.
+    Identifier,
+    /// A string including its quotes, references are not resolved.
+    String,
+    Number,
+    Bool,
+    Null,
+}
+
+impl TokenKind {
+    /// Whether the token is whitespace or a comment.
+    pub fn is_trivia(self) -> bool {
+        matches!(
+            self,
+            TokenKind::Whitespace | TokenKind::LineComment | TokenKind::BlockComment
+        )
+    }
+}
+
+/// A piece of the document as it is written. The span is where the token was parsed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Token {
+    kind: TokenKind,
+    text: String,
+    span: Span,
+}
+
+impl Token {
+    pub fn kind(&self) -> TokenKind {
+        self.kind
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn span(&self) -> Span {
+        self.span
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Node {
+    kind: NodeKind,
+    children: Vec<Element>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Element {
+    Node(Node),
+    Token(Token),
+}
+
+impl Element {
+    /// Whether the element is whitespace or a comment.
+    pub fn is_trivia(&self) -> bool {
+        match self {
+            Element::Token(token) => token.kind.is_trivia(),
+            Element::Node(_) => false,
+        }
+    }
+
+    fn push_tokens<'a>(&'a self, tokens: &mut Vec<&'a Token>) {
+        match self {
+            Element::Node(node) => node.push_tokens(tokens),
+            Element::Token(token) => tokens.push(token),
+        }
+    }
+}
+
+impl Node {
+    pub fn kind(&self) -> NodeKind {
+        self.kind
+    }
+
+    pub fn children(&self) -> &[Element] {
+        &self.children
+    }
+
+    /// Every token of the node in the order they are written.
+    pub fn tokens(&self) -> Vec<&Token> {
+        let mut tokens = Vec::new();
+        self.push_tokens(&mut tokens);
+        tokens
+    }
+
+    fn push_tokens<'a>(&'a self, tokens: &mut Vec<&'a Token>) {
+        for child in &self.children {
+            child.push_tokens(tokens);
+        }
+    }
+
+    /// From the start of the first token to the end of the last one.
+    pub fn span(&self) -> Span {
+        let tokens = self.tokens();
+        match (tokens.first(), tokens.last()) {
+            (Some(first), Some(last)) => Span {
+                end: last.span.end,
+                ..first.span
+            },
+            _ => Span::default(),
+        }
+    }
+
+    /// The members of an object.
+    pub fn members(&self) -> impl Iterator<Item = &Node> {
+        self.children.iter().filter_map(|child| match child {
+            Element::Node(node) if node.kind == NodeKind::Member => Some(node),
+            _ => None,
+        })
+    }
+
+    /// The values of an array.
+    pub fn elements(&self) -> impl Iterator<Item = &Element> {
+        self.children.iter().filter(|child| match child {
+            Element::Node(_) => true,
+            Element::Token(token) => is_value(token.kind),
+        })
+    }
+
+    /// The key of a member.
+    pub fn key(&self) -> Option<&Token> {
+        match self.children.first() {
+            Some(Element::Token(token)) if self.kind == NodeKind::Member => Some(token),
+            _ => None,
+        }
+    }
+
+    /// The value of a member or of the document.
+    pub fn value(&self) -> Option<&Element> {
+        let skip = match self.kind {
+            NodeKind::Member => 1,
+            NodeKind::Document => 0,
+            NodeKind::Object | NodeKind::Array => return None,
+        };
+        self.children.iter().skip(skip).find(|child| match child {
+            Element::Node(_) => true,
+            Element::Token(token) => is_value(token.kind),
+        })
+    }
+}
+
+pub(crate) fn is_value(kind: TokenKind) -> bool {
+    matches!(
+        kind,
+        TokenKind::String | TokenKind::Number | TokenKind::Bool | TokenKind::Null
+    )
+}
+
+impl fmt::Display for Node {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for token in self.tokens() {
+            f.write_str(&token.text)?;
+        }
+        Ok(())
+    }
+}
+
+impl Document {
+    pub fn parse(input: &str) -> Result<Self> {
+        let pair = Tokenizer::parse(Rule::root, input)?
+            .next()
+            .expect("failed to parse the file");
+        let mut builder = Builder::new(input);
+        let mut children = Vec::new();
+        builder.push_gap(pair.as_span().start(), &mut children);
+        children.push(builder.build(pair));
+        builder.push_gap(input.len(), &mut children);
+        Ok(Self {
+            root: Node {
+                kind: NodeKind::Document,
+                children,
+            },
+        })
+    }
+
+    /// The node of kind [`NodeKind::Document`] that holds everything else.
+    pub fn root(&self) -> &Node {
+        &self.root
+    }
+
+    /// Parses the document into a value, with its references resolved.
+    pub fn to_value(&self) -> Result<Value> {
+        crate::parse(&self.to_string())
+    }
+
+    pub fn to_value_with_options(&self, options: &ParseOptions) -> Result<Value> {
+        crate::parse_with_options(&self.to_string(), options)
+    }
+}
+
+impl FromStr for Document {
+    type Err = crate::Error;
+
+    fn from_str(input: &str) -> Result<Self> {
+        Self::parse(input)
+    }
+}
+
+impl fmt::Display for Document {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.root.fmt(f)
+    }
+}
+
+/// Turns the parsed pairs into nodes. Whitespace, comments and brackets aren't part of the
+/// pairs, they are found in the text between them.
+struct Builder<'a> {
+    input: &'a str,
+    position: usize,
+    line: usize,
+    column: usize,
+}
+
+impl<'a> Builder<'a> {
+    fn new(input: &'a str) -> Self {
+        Self {
+            input,
+            position: 0,
+            line: 1,
+            column: 1,
+        }
+    }
+
+    fn build(&mut self, pair: Pair<Rule>) -> Element {
+        let kind = match pair.as_rule() {
+            Rule::object => NodeKind::Object,
+            Rule::array => NodeKind::Array,
+            Rule::pair => NodeKind::Member,
+            rule => {
+                let kind = match rule {
+                    Rule::identifier => TokenKind::Identifier,
+                    Rule::string => TokenKind::String,
+                    Rule::number => TokenKind::Number,
+                    Rule::bool => TokenKind::Bool,
+                    Rule::null => TokenKind::Null,
+                    Rule::comma => TokenKind::Comma,
+                    Rule::colon => TokenKind::Colon,
+                    _ => unreachable!("unknown token"),
+                };
+                return Element::Token(self.take(kind, pair.as_span().end()));
+            }
+        };
+        let end = pair.as_span().end();
+        let mut children = Vec::new();
+        for pair in pair.into_inner() {
+            self.push_gap(pair.as_span().start(), &mut children);
+            children.push(self.build(pair));
+        }
+        self.push_gap(end, &mut children);
+        Element::Node(Node { kind, children })
+    }
+
+    /// Adds the whitespace, comments and brackets up to `end`.
+    fn push_gap(&mut self, end: usize, children: &mut Vec<Element>) {
+        while self.position < end {
+            let rest = &self.input[self.position..end];
+            let (kind, length) = if rest.starts_with('#') || rest.starts_with("//") {
+                let length = rest.find('\n').unwrap_or(rest.len());
+                (TokenKind::LineComment, length)
+            } else if rest.starts_with("/*") {
+                let length = rest.find("*/").map_or(rest.len(), |x| x + 2);
+                (TokenKind::BlockComment, length)
+            } else {
+                match rest.chars().next() {
+                    Some('{') => (TokenKind::OpenBrace, 1),
+                    Some('}') => (TokenKind::CloseBrace, 1),
+                    Some('[') => (TokenKind::OpenBracket, 1),
+                    Some(']') => (TokenKind::CloseBracket, 1),
+                    Some(',') => (TokenKind::Comma, 1),
+                    Some(':') => (TokenKind::Colon, 1),
+                    _ => {
+                        let length = rest
+                            .find(|c: char| !matches!(c, ' ' | '\t' | '\r' | '\n'))
+                            .unwrap_or(rest.len());
+                        // Anything unexpected is kept as whitespace so that nothing is lost
+                        let length = length.max(rest.chars().next().map_or(1, char::len_utf8));
+                        (TokenKind::Whitespace, length)
+                    }
+                }
+            };
+            let token = self.take(kind, self.position + length);
+            children.push(Element::Token(token));
+        }
+    }
+
+    fn take(&mut self, kind: TokenKind, end: usize) -> Token {
+        let text = &self.input[self.position..end];
+        let span = Span {
+            start: self.position,
+            end,
+            line: self.line,
+            column: self.column,
+        };
+        for c in text.chars() {
+            if c == '\n' {
+                self.line += 1;
+                self.column = 1;
+            } else {
+                self.column += 1;
+            }
+        }
+        self.position = end;
+        Token {
+            kind,
+            text: text.to_string(),
+            span,
+        }
+    }
+}
@@ -1,7 +1,221 @@
+use crate::options::Extension;
+use crate::tokenizer::Rule;
+use pest::error::{ErrorVariant, InputLocation, LineColLocation};
+use std::fmt;
 use thiserror::Error as ThisError;
 
+/// A location in the parsed input. Lines and columns start at 1 while `start` and `end` are
+/// byte offsets, e.g. `&input[span.start..span.end]`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Span {
+    /// The span between two byte offsets of the input.
+    pub(crate) fn from_offsets(input: &str, start: usize, end: usize) -> Self {
+        let span = pest::Span::new(input, start, end).expect("offsets should be in the input");
+        Self::from_pest(span)
+    }
+
+    pub(crate) fn from_pest(span: pest::Span<'_>) -> Self {
+        let (line, column) = span.start_pos().line_col();
+        Self {
+            start: span.start(),
+            end: span.end(),
+            line,
+            column,
+        }
+    }
+}
+
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
 #[derive(ThisError, Debug)]
 pub enum Error {
-    #[error("{0}")]
-    Parsing(String),
+    #[error("{message} at {span}")]
+    Syntax { message: String, span: Span },
+    /// An extension of JSON that has been turned off, see [`ParseOptions`](crate::ParseOptions).
+    #[error("Unsupported {extension} at {span}")]
+    Extension { extension: Extension, span: Span },
+    #[error("Object already contains key: {key} at {span}")]
+    DuplicateKey { key: String, span: Span },
+    #[error("Invalid escape {escape} at {span}")]
+    InvalidEscape { escape: String, span: Span },
+    #[error("Invalid number {number} at {span}")]
+    InvalidNumber { number: String, span: Span },
+    #[error("{message} at {span}")]
+    InvalidReference { message: String, span: Span },
+    #[error("Unresolved reference {target}, {missing} was not found at {span}")]
+    UnresolvedReference {
+        target: String,
+        missing: String,
+        span: Span,
+        /// Where the deepest existing part of the target is defined, e.g. the key `color` of
+        /// an unresolved `color.rde`.
+        parent: Option<Box<Span>>,
+        /// A similar target that does exist, e.g. `color.red`.
+        suggestion: Option<String>,
+    },
+    #[error("Cyclic reference: {} at {span}", .cycle.join(" -> "))]
+    CyclicReference { cycle: Vec<String>, span: Span },
+    #[error("Cyclic import: {} at {span}", .cycle.join(" -> "))]
+    CyclicImport { cycle: Vec<String>, span: Span },
+    #[error("Failed to read {path}: {message} at {span}")]
+    Io {
+        path: String,
+        message: String,
+        span: Span,
+    },
+    #[error("{source} in {path}, imported at {span}")]
+    Import {
+        path: String,
+        span: Span,
+        source: Box<Error>,
+    },
+    /// A value didn't fit the type it was deserialized into, see
+    /// [`from_str`](crate::from_str).
+    #[error("{message} at {span}")]
+    Deserialize { message: String, span: Span },
+    /// A value couldn't be written, see [`to_string`](crate::to_string).
+    #[error("{message}")]
+    Serialize { message: String },
+    /// A [`Document`](crate::Document) couldn't be edited at the path.
+    #[error("Cannot edit {path}: {message}")]
+    Edit { path: String, message: String },
+    /// Every error that was found when recovering from errors, see
+    /// [`ParseOptions::recover`](crate::ParseOptions::recover).
+    #[error("{}", format_errors(.errors))]
+    Multiple { errors: Vec<Error> },
+}
+
+impl Error {
+    /// Where the error occurred. For errors in imported files this is where the file was
+    /// referenced, the span inside of the imported file is found on the source error.
+    pub fn span(&self) -> Span {
+        match self {
+            Error::Syntax { span, .. }
+            | Error::Extension { span, .. }
+            | Error::DuplicateKey { span, .. }
+            | Error::InvalidEscape { span, .. }
+            | Error::InvalidNumber { span, .. }
+            | Error::InvalidReference { span, .. }
+            | Error::UnresolvedReference { span, .. }
+            | Error::CyclicReference { span, .. }
+            | Error::CyclicImport { span, .. }
+            | Error::Io { span, .. }
+            | Error::Import { span, .. }
+            | Error::Deserialize { span, .. } => *span,
+            Error::Serialize { .. } | Error::Edit { .. } => Span::default(),
+            Error::Multiple { errors } => errors.first().map(Error::span).unwrap_or_default(),
+        }
+    }
+
+    /// The errors that were found, which is more than one for [`Error::Multiple`].
+    pub fn errors(&self) -> &[Error] {
+        match self {
+            Error::Multiple { errors } => errors,
+            _ => std::slice::from_ref(self),
+        }
+    }
+}
+
+fn format_errors(errors: &[Error]) -> String {
+    errors
+        .iter()
+        .map(Error::to_string)
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+impl From<pest::error::Error<Rule>> for Error {
+    fn from(error: pest::error::Error<Rule>) -> Self {
+        let (start, end) = match error.location {
+            InputLocation::Pos(position) => (position, position),
+            InputLocation::Span(span) => span,
+        };
+        let (line, column) = match error.line_col {
+            LineColLocation::Pos(line_col) => line_col,
+            LineColLocation::Span(line_col, _) => line_col,
+        };
+        let message = match error.variant {
+            ErrorVariant::ParsingError {
+                positives,
+                negatives,
+            } => {
+                if positives.is_empty() && negatives.is_empty() {
+                    "Unknown syntax error".to_string()
+                } else if positives.is_empty() {
+                    format!("Unexpected {}", format_rules(&negatives))
+                } else {
+                    format!("Expected {}", format_rules(&positives))
+                }
+            }
+            ErrorVariant::CustomError { message } => message,
+        };
+        Error::Syntax {
+            message,
+            span: Span {
+                start,
+                end,
+                line,
+                column,
+            },
+        }
+    }
+}
+
+fn format_rules(rules: &[Rule]) -> String {
+    let mut names = Vec::new();
+    for name in rules.iter().map(get_rule_name) {
+        if !names.contains(&name) {
+            names.push(name);
+        }
+    }
+    match names.split_last() {
+        Some((last, rest)) if !rest.is_empty() => format!("{} or {}", rest.join(", "), last),
+        _ => names.join(""),
+    }
+}
+
+/// Names a rule by what it is in a document, rules that are parts of the same thing share
+/// its name.
+fn get_rule_name(rule: &Rule) -> String {
+    let name = match rule {
+        Rule::pair | Rule::identifier => "key",
+        Rule::int | Rule::exp => "number",
+        Rule::text | Rule::reference_start => "text",
+        Rule::esc_quote_double
+        | Rule::esc_quote_single
+        | Rule::esc_backslash
+        | Rule::esc_slash
+        | Rule::esc_backspace
+        | Rule::esc_form_feed
+        | Rule::esc_new_line
+        | Rule::esc_carriage_return
+        | Rule::esc_tab
+        | Rule::esc_unicode
+        | Rule::esc_reference => "escape",
+        Rule::reference
+        | Rule::reference_variable
+        | Rule::reference_file
+        | Rule::reference_file_name
+        | Rule::reference_path
+        | Rule::reference_scope
+        | Rule::reference_key
+        | Rule::reference_index
+        | Rule::reference_literal
+        | Rule::reference_default => "reference",
+        Rule::comment_block_style | Rule::comment_line_style => "comment",
+        Rule::EOI => "end of input",
+        _ => return format!("{:?}", rule).replace('_', " "),
+    };
+    name.to_string()
 }
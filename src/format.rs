@@ -0,0 +1,238 @@
+use crate::document::{is_value, Document, Element, Node, NodeKind, Token, TokenKind};
+use crate::options::ParseOptions;
+use crate::parser::parse_key;
+use crate::ser::is_identifier;
+use crate::Result;
+
+const INDENT: &str = "    ";
+const MAX_WIDTH: usize = 80;
+
+/// Formats a document in the one canonical style of d4t4, keeping every comment:
+///
+/// - Values are indented by four spaces and objects have one key per line
+/// - Arrays of numbers, strings etc. stay on one line as long as it is at most 80 characters
+/// - Keys are unquoted where possible, strings use double quotes unless that needs more
+///   escapes than single quotes
+/// - Values on separate lines end with a comma, including the last one
+/// - Comments are on their own line, or after a value if they were on the same line as it
+/// - At most one empty line is kept between values
+///
+/// ```rust
+///     let input = "{'a':1, # one\n\n\n  'b c': [ 'x',2 ]}";
+///     let formatted = d4t4::format(input).unwrap();
+///     assert_eq!(formatted, "{\n    a: 1, # one\n\n    \"b c\": [\"x\", 2],\n}\n");
+///     assert!(d4t4::is_formatted(&formatted).unwrap());
+/// ```
+pub fn format(input: &str) -> Result<String> {
+    Ok(format_document(&Document::parse(input)?))
+}
+
+/// Whether [`format`] would leave the input as it is.
+pub fn is_formatted(input: &str) -> Result<bool> {
+    Ok(format(input)? == input)
+}
+
+/// Formats a parsed document, see [`format`].
+pub fn format_document(document: &Document) -> String {
+    let lines = get_lines(document.root(), 0);
+    let mut output = String::new();
+    write_lines(&mut output, lines, 0);
+    output
+}
+
+/// A line of an object, an array or the document.
+enum Line {
+    Comment(String),
+    Value(String, Option<String>),
+    Empty,
+}
+
+/// Splits the children of a node into values and comments, values are formatted for the
+/// given depth.
+fn get_lines(node: &Node, depth: usize) -> Vec<Line> {
+    let mut lines = Vec::new();
+    let mut is_empty_line = false;
+    // Whether a comment would be on the same line as the last value
+    let mut is_same_line = false;
+    for child in node.children() {
+        match child {
+            Element::Token(token) if token.kind() == TokenKind::Whitespace => {
+                let new_lines = token.text().matches('\n').count();
+                if new_lines > 0 {
+                    is_same_line = false;
+                }
+                is_empty_line |= new_lines > 1 && !lines.is_empty();
+            }
+            Element::Token(token) if token.kind().is_trivia() => {
+                let comment = token.text().trim_end().to_string();
+                if let (true, Some(Line::Value(_, trailing @ None))) =
+                    (is_same_line, lines.last_mut())
+                {
+                    *trailing = Some(comment);
+                    continue;
+                }
+                push_line(&mut lines, &mut is_empty_line, Line::Comment(comment));
+            }
+            Element::Token(token) if !is_value(token.kind()) => {}
+            Element::Node(member) if member.kind() == NodeKind::Member => {
+                let mut value = String::new();
+                for child in member.children() {
+                    match child {
+                        Element::Token(token) if token.kind() == TokenKind::Whitespace => {}
+                        Element::Token(token) if token.kind().is_trivia() => {
+                            let comment = token.text().trim_end().to_string();
+                            push_line(&mut lines, &mut is_empty_line, Line::Comment(comment));
+                        }
+                        Element::Token(token) if token.kind() == TokenKind::Colon => {
+                            value.push_str(": ");
+                        }
+                        Element::Token(token) if value.is_empty() => {
+                            value.push_str(&format_key(token));
+                        }
+                        child => value.push_str(&format_value(child, depth)),
+                    }
+                }
+                push_line(&mut lines, &mut is_empty_line, Line::Value(value, None));
+                is_same_line = true;
+            }
+            child => {
+                let value = format_value(child, depth);
+                push_line(&mut lines, &mut is_empty_line, Line::Value(value, None));
+                is_same_line = true;
+            }
+        }
+    }
+    lines
+}
+
+fn push_line(lines: &mut Vec<Line>, is_empty_line: &mut bool, line: Line) {
+    if *is_empty_line {
+        lines.push(Line::Empty);
+        *is_empty_line = false;
+    }
+    lines.push(line);
+}
+
+fn format_value(element: &Element, depth: usize) -> String {
+    let node = match element {
+        Element::Token(token) if token.kind() == TokenKind::String => {
+            return format_string(token.text())
+        }
+        Element::Token(token) => return token.text().to_string(),
+        Element::Node(node) => node,
+    };
+    let (open, close) = match node.kind() {
+        NodeKind::Array => ('[', ']'),
+        _ => ('{', '}'),
+    };
+    let lines = get_lines(node, depth + 1);
+    if lines.is_empty() {
+        return format!("{}{}", open, close);
+    }
+    if node.kind() == NodeKind::Array {
+        if let Some(values) = get_single_line(node, &lines) {
+            let line = format!("{}{}{}", open, values.join(", "), close);
+            if depth * INDENT.len() + line.chars().count() <= MAX_WIDTH {
+                return line;
+            }
+        }
+    }
+    let mut output = open.to_string();
+    output.push('\n');
+    write_lines(&mut output, lines, depth + 1);
+    output.push_str(&INDENT.repeat(depth));
+    output.push(close);
+    output
+}
+
+/// The values of an array that only holds numbers, strings etc. and no comments.
+fn get_single_line<'a>(node: &Node, lines: &'a [Line]) -> Option<Vec<&'a str>> {
+    if node
+        .elements()
+        .any(|element| matches!(element, Element::Node(_)))
+    {
+        return None;
+    }
+    lines
+        .iter()
+        .map(|line| match line {
+            Line::Value(value, None) => Some(value.as_str()),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Writes the lines of the document, or of an object or array with a trailing comma after
+/// every value.
+fn write_lines(output: &mut String, lines: Vec<Line>, depth: usize) {
+    let indent = INDENT.repeat(depth);
+    let comma = if depth > 0 { "," } else { "" };
+    for line in lines {
+        match line {
+            Line::Comment(comment) => {
+                output.push_str(&indent);
+                output.push_str(&comment);
+            }
+            Line::Value(value, trailing) => {
+                output.push_str(&indent);
+                output.push_str(&value);
+                output.push_str(comma);
+                if let Some(comment) = trailing {
+                    output.push(' ');
+                    output.push_str(&comment);
+                }
+            }
+            Line::Empty => {}
+        }
+        output.push('\n');
+    }
+}
+
+fn format_key(token: &Token) -> String {
+    if token.kind() == TokenKind::Identifier {
+        return token.text().to_string();
+    }
+    match parse_key(token.text(), &ParseOptions::default()) {
+        Ok(key) if is_identifier(&key) => key,
+        _ => format_string(token.text()),
+    }
+}
+
+/// Rewrites a string in double quotes, or in single quotes if it holds more double quotes
+/// than single ones. Strings with quotes inside of references keep their quotes, as they
+/// can't be escaped there.
+fn format_string(text: &str) -> String {
+    let quote = text.chars().next().unwrap_or('"');
+    let content = &text[1..text.len() - 1];
+    let count = |c: char| content.matches(c).count();
+    let has_quotes = count('"') + count('\'') > 0;
+    if has_quotes && content.contains("&{") {
+        return text.to_string();
+    }
+    let new_quote = if count('"') > count('\'') { '\'' } else { '"' };
+    if new_quote == quote {
+        return text.to_string();
+    }
+    let mut output = String::with_capacity(text.len());
+    output.push(new_quote);
+    let mut chars = content.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some(escaped) if escaped == quote => output.push(escaped),
+                Some(escaped) => {
+                    output.push('\\');
+                    output.push(escaped);
+                }
+                None => output.push('\\'),
+            },
+            c if c == new_quote => {
+                output.push('\\');
+                output.push(c);
+            }
+            c => output.push(c),
+        }
+    }
+    output.push(new_quote);
+    output
+}
@@ -10,11 +10,33 @@
 //!
 //! ## Features
 //! - JSON-compatible
+//! - Any value may be at the root, a string at the root can still reference environment variables and files
 //! - Objects & arrays may have trailing comma
 //! - Object keys may not need quotes
 //! - Single & multiline comments are allowed
 //! - Strings may use double and/or single quotes
 //! - Values can be referenced from other parts of the object tree
+//! - Array elements can be referenced by index, e.g. `&{colors[0]}` or `&{colors[-1]}`
+//! - A string that only holds a reference, e.g. `"&{size}"`, keeps the type of the referenced value
+//! - References may point to other references, cyclic references are reported as errors
+//! - References may be relative to the value holding them: `&{.sibling}`, `&{..parent_key}` or `&{^.parent_key}`
+//! - Missing references may fall back to other references or literals: `&{accent ?? primary ?? '#000'}` or `&{accent:-#000}`
+//! - Environment variables can be referenced with `&{env:DATABASE_URL}`, see `ParseOptions` for custom sources
+//! - Other files can be referenced with `&{file:./colors.d4t4#red}`, relative to the referencing file
+//! - A literal `&{` can be written as `\&{`
+//! - Every extension of JSON can be turned off with `ParseOptions`, e.g. `ParseOptions::new().trailing_commas(false)`
+//! - `ParseOptions::strict()` only accepts plain JSON and names every extension that is used
+//! - The `arbitrary_precision` feature keeps numbers exactly as they are written, read them as `i128` or `u128` with serde or `value::Value::as_i128`, or as a decimal string through `Number`
+//! - Objects keep their keys in the order they are written, turn off the default `preserve_order` feature to sort them instead. Cargo unifies the feature with `serde_json/preserve_order`, so keys keep their order if any other crate of the build turns that on
+//! - `Document` keeps every token and comment, writing it back gives the exact same text
+//! - `Document` can be edited with `set`, `insert`, `remove` and `rename_key`, only the edited tokens change
+//! - Any serde type can be written as d4t4 with `to_string`, `to_string_pretty` or `to_writer`, `WriteOptions` sets indentation, quotes, unquoted keys and trailing commas
+//! - `format` rewrites a document in one canonical style and keeps every comment, `d4t4 fmt [--check] [FILE]...` does the same from the command line
+//! - `parse_spanned` returns a `value::Value` that keeps the line, column and comments of every value and the order of keys, it converts to and from `serde_json::Value`
+//! - `from_str` and `from_reader` deserialize a document straight into your types with serde, errors point out the line and column of the value
+//! - Errors point out their line and column, `Error::diagnostic` renders them with a snippet of the source and suggestions
+//! - All errors can be collected at once with `ParseOptions::recover`, syntax errors are skipped up to the next `,`, `}` or `]`
+//! - Warnings, e.g. about unused definitions or references that resolve to empty strings, are returned by `parse_with_warnings`
 //!
 //! ## Example
 //! ```rust
@@ -83,11 +105,34 @@
 
 #![allow(clippy::needless_doctest_main)]
 
+mod de;
+mod diagnostic;
+mod document;
 mod error;
+mod format;
+mod options;
 mod parser;
+mod ser;
 mod tokenizer;
+pub mod value;
+mod warning;
 
-pub use crate::error::Error;
-pub use crate::parser::parse;
-pub use anyhow::Result;
+pub use crate::de::{from_reader, from_reader_with_options, from_str, from_str_with_options};
+pub use crate::diagnostic::{Diagnostic, Label};
+pub use crate::document::{Document, Element, Node, NodeKind, Token, TokenKind};
+pub use crate::error::{Error, Span};
+pub use crate::format::{format, format_document, is_formatted};
+pub use crate::options::{
+    DuplicateKeys, Environment, Extension, FileSystem, Files, ParseOptions, Root, Variables,
+    WriteOptions,
+};
+pub use crate::parser::{parse, parse_with_options, parse_with_warnings};
+pub use crate::ser::{
+    to_string, to_string_pretty, to_string_with_options, to_writer, to_writer_pretty,
+    to_writer_with_options, Serializer,
+};
+pub use crate::value::{parse_spanned, parse_spanned_with_options};
+pub use crate::warning::Warning;
 pub use serde_json::Value;
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
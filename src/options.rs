@@ -0,0 +1,406 @@
+use std::collections::{HashMap, HashSet};
+use std::env;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// A source of variables that can be referenced with `&{env:NAME}`.
+pub trait Variables {
+    fn get(&self, name: &str) -> Option<String>;
+}
+
+/// Reads variables from the environment of the current process.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Environment;
+
+impl Variables for Environment {
+    fn get(&self, name: &str) -> Option<String> {
+        env::var(name).ok()
+    }
+}
+
+impl Variables for HashMap<String, String> {
+    fn get(&self, name: &str) -> Option<String> {
+        HashMap::get(self, name).cloned()
+    }
+}
+
+/// A source of the files that can be referenced with `&{file:./colors.d4t4#red}`.
+pub trait Files {
+    fn read(&self, path: &Path) -> io::Result<String>;
+}
+
+/// Reads files from the file system.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct FileSystem;
+
+impl Files for FileSystem {
+    fn read(&self, path: &Path) -> io::Result<String> {
+        fs::read_to_string(path)
+    }
+}
+
+impl Files for HashMap<PathBuf, String> {
+    fn read(&self, path: &Path) -> io::Result<String> {
+        self.get(path)
+            .cloned()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "file not found"))
+    }
+}
+
+/// The parts of d4t4 that go beyond JSON, each of them can be turned off separately with
+/// [`ParseOptions`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Extension {
+    TrailingComma,
+    UnquotedKey,
+    SingleQuotedString,
+    /// `\'`, which is only needed in single-quoted strings.
+    EscapedSingleQuote,
+    /// `# comment` or `// comment`
+    LineComment,
+    /// `/* comment */`
+    BlockComment,
+    /// `&{path}`, which is plain text when references are turned off.
+    Reference,
+    /// Tabs, new lines etc. that are written as is inside of a string.
+    ControlCharacter,
+}
+
+impl Extension {
+    pub const ALL: [Extension; 8] = [
+        Extension::TrailingComma,
+        Extension::UnquotedKey,
+        Extension::SingleQuotedString,
+        Extension::EscapedSingleQuote,
+        Extension::LineComment,
+        Extension::BlockComment,
+        Extension::Reference,
+        Extension::ControlCharacter,
+    ];
+
+    /// The option that allows the extension.
+    pub(crate) fn option_name(self) -> &'static str {
+        match self {
+            Extension::TrailingComma => "trailing_commas",
+            Extension::UnquotedKey => "unquoted_keys",
+            Extension::SingleQuotedString | Extension::EscapedSingleQuote => "single_quotes",
+            Extension::LineComment => "line_comments",
+            Extension::BlockComment => "block_comments",
+            Extension::Reference => "references",
+            Extension::ControlCharacter => "control_characters",
+        }
+    }
+}
+
+impl fmt::Display for Extension {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Extension::TrailingComma => "trailing comma",
+            Extension::UnquotedKey => "unquoted key",
+            Extension::SingleQuotedString => "single-quoted string",
+            Extension::EscapedSingleQuote => "escaped single quote",
+            Extension::LineComment => "line comment",
+            Extension::BlockComment => "block comment",
+            Extension::Reference => "reference",
+            Extension::ControlCharacter => "unescaped control character",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// What happens when an object contains the same key more than once.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DuplicateKeys {
+    /// Fail with [`Error::DuplicateKey`](crate::Error::DuplicateKey).
+    Error,
+    /// Keep the value that comes first.
+    First,
+    /// Keep the value that comes last, like most JSON parsers do.
+    Last,
+}
+
+/// Which values a document may consist of at the top level.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Root {
+    Object,
+    ObjectOrArray,
+    /// Any JSON value, e.g. a single string or number.
+    Any,
+}
+
+/// The syntax that is accepted by the parser.
+#[derive(Debug, Clone)]
+pub(crate) struct Dialect {
+    disabled: HashSet<Extension>,
+    pub duplicate_keys: DuplicateKeys,
+    pub root: Root,
+}
+
+impl Dialect {
+    pub fn allows(&self, extension: Extension) -> bool {
+        !self.disabled.contains(&extension)
+    }
+}
+
+impl Default for Dialect {
+    fn default() -> Self {
+        Self {
+            disabled: HashSet::new(),
+            duplicate_keys: DuplicateKeys::Error,
+            root: Root::Any,
+        }
+    }
+}
+
+/// Options used by [`parse_with_options`](crate::parse_with_options).
+///
+/// ```rust
+///     use d4t4::{parse_with_options, ParseOptions};
+///     use std::collections::HashMap;
+///
+///     let mut variables = HashMap::new();
+///     variables.insert("HOST".to_string(), "localhost".to_string());
+///
+///     let options = ParseOptions::new().variables(variables);
+///     let json = parse_with_options(r#"{ url: "http://&{env:HOST}:&{env:PORT:-8080}" }"#, &options).unwrap();
+///     assert_eq!(json["url"], "http://localhost:8080");
+/// ```
+///
+/// Every extension of d4t4 is allowed by default, but can be turned off:
+///
+/// ```rust
+///     use d4t4::{parse_with_options, ParseOptions};
+///
+///     let options = ParseOptions::new().trailing_commas(false);
+///     let error = parse_with_options("{ a: 1, }", &options).unwrap_err();
+///     assert_eq!(error.to_string(), "Unsupported trailing comma at 1:7");
+/// ```
+pub struct ParseOptions {
+    variables: Box<dyn Variables>,
+    files: Box<dyn Files>,
+    path: Option<PathBuf>,
+    recover: bool,
+    dialect: Dialect,
+}
+
+impl ParseOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Options that only accept plain JSON as of RFC 8259. Every extension of d4t4 is
+    /// rejected with an [`Error::Extension`](crate::Error::Extension) that names it, and
+    /// `&{...}` is plain text.
+    ///
+    /// ```rust
+    ///     use d4t4::{parse_with_options, ParseOptions};
+    ///
+    ///     let error = parse_with_options("{ 'a': 1 }", &ParseOptions::strict()).unwrap_err();
+    ///     assert_eq!(error.to_string(), "Unsupported single-quoted string at 1:3");
+    /// ```
+    pub fn strict() -> Self {
+        Extension::ALL
+            .iter()
+            .fold(Self::new(), |options, extension| {
+                options.extension(*extension, false)
+            })
+    }
+
+    /// Sets where `&{env:NAME}` references are read from, defaults to [`Environment`].
+    pub fn variables<T: Variables + 'static>(mut self, variables: T) -> Self {
+        self.variables = Box::new(variables);
+        self
+    }
+
+    /// Sets where `&{file:...}` references are read from, defaults to [`FileSystem`].
+    pub fn files<T: Files + 'static>(mut self, files: T) -> Self {
+        self.files = Box::new(files);
+        self
+    }
+
+    /// Sets the path of the parsed document, referenced files are relative to it.
+    pub fn path<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.path = Some(path.into());
+        self
+    }
+
+    /// Keeps parsing after errors so that all of them are reported at once, as
+    /// [`Error::Multiple`](crate::Error::Multiple) if there is more than one. Syntax errors
+    /// are skipped up to the next `,`, `}` or `]`.
+    pub fn recover(mut self, recover: bool) -> Self {
+        self.recover = recover;
+        self
+    }
+
+    /// Allows or rejects an extension of JSON, see the shorthands like
+    /// [`trailing_commas`](Self::trailing_commas).
+    pub fn extension(mut self, extension: Extension, allow: bool) -> Self {
+        if allow {
+            self.dialect.disabled.remove(&extension);
+        } else {
+            self.dialect.disabled.insert(extension);
+        }
+        self
+    }
+
+    /// Allows a comma after the last value of an object or array, e.g. `[1, 2,]`.
+    pub fn trailing_commas(self, allow: bool) -> Self {
+        self.extension(Extension::TrailingComma, allow)
+    }
+
+    /// Allows keys without quotes, e.g. `{ name: 'Karlsson' }`.
+    pub fn unquoted_keys(self, allow: bool) -> Self {
+        self.extension(Extension::UnquotedKey, allow)
+    }
+
+    /// Allows strings in single quotes, and with that the `\'` escape.
+    pub fn single_quotes(self, allow: bool) -> Self {
+        self.extension(Extension::SingleQuotedString, allow)
+            .extension(Extension::EscapedSingleQuote, allow)
+    }
+
+    /// Allows `# comments` and `// comments`.
+    pub fn line_comments(self, allow: bool) -> Self {
+        self.extension(Extension::LineComment, allow)
+    }
+
+    /// Allows `/* comments */`.
+    pub fn block_comments(self, allow: bool) -> Self {
+        self.extension(Extension::BlockComment, allow)
+    }
+
+    /// Resolves references like `&{path}`. Without references they are kept as plain text
+    /// and the `\&{` escape is rejected.
+    pub fn references(self, allow: bool) -> Self {
+        self.extension(Extension::Reference, allow)
+    }
+
+    /// Allows tabs, new lines and other control characters to be written as is in strings.
+    pub fn control_characters(self, allow: bool) -> Self {
+        self.extension(Extension::ControlCharacter, allow)
+    }
+
+    /// Sets what happens when an object contains the same key twice, defaults to
+    /// [`DuplicateKeys::Error`].
+    pub fn duplicate_keys(mut self, duplicate_keys: DuplicateKeys) -> Self {
+        self.dialect.duplicate_keys = duplicate_keys;
+        self
+    }
+
+    /// Sets which values are allowed at the top level, defaults to [`Root::Any`].
+    pub fn root(mut self, root: Root) -> Self {
+        self.dialect.root = root;
+        self
+    }
+
+    pub(crate) fn get_variables(&self) -> &dyn Variables {
+        self.variables.as_ref()
+    }
+
+    pub(crate) fn get_files(&self) -> &dyn Files {
+        self.files.as_ref()
+    }
+
+    pub(crate) fn get_path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    pub(crate) fn get_recover(&self) -> bool {
+        self.recover
+    }
+
+    pub(crate) fn get_dialect(&self) -> &Dialect {
+        &self.dialect
+    }
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        Self {
+            variables: Box::new(Environment),
+            files: Box::new(FileSystem),
+            path: None,
+            recover: false,
+            dialect: Dialect::default(),
+        }
+    }
+}
+
+impl fmt::Debug for ParseOptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ParseOptions")
+            .field("path", &self.path)
+            .field("recover", &self.recover)
+            .field("dialect", &self.dialect)
+            .finish()
+    }
+}
+
+/// Options used by [`to_string_with_options`](crate::to_string_with_options).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WriteOptions {
+    pub(crate) indent: String,
+    pub(crate) unquoted_keys: bool,
+    pub(crate) quote: char,
+    pub(crate) trailing_commas: bool,
+}
+
+impl WriteOptions {
+    /// Options that write JSON on a single line, apart from `&{` in strings, see
+    /// [`to_string`](crate::to_string).
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Options that write JSON with every value on its own line, indented by four spaces.
+    pub fn pretty() -> Self {
+        Self::new().indent("    ")
+    }
+
+    /// Sets what values are indented with for every level of nesting. Without an indent
+    /// everything is written on a single line.
+    pub fn indent<T: Into<String>>(mut self, indent: T) -> Self {
+        self.indent = indent.into();
+        self
+    }
+
+    /// Writes keys without quotes where possible, e.g. `{ name: 'Karlsson' }`.
+    pub fn unquoted_keys(mut self, unquoted_keys: bool) -> Self {
+        self.unquoted_keys = unquoted_keys;
+        self
+    }
+
+    /// Sets the quote that strings and keys are written with, either `"` or `'`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the quote is neither of them.
+    pub fn quote(mut self, quote: char) -> Self {
+        assert!(
+            quote == '"' || quote == '\'',
+            "strings can only be quoted with \" or '"
+        );
+        self.quote = quote;
+        self
+    }
+
+    /// Writes a comma after the last value of an object or array when they are written on
+    /// multiple lines.
+    pub fn trailing_commas(mut self, trailing_commas: bool) -> Self {
+        self.trailing_commas = trailing_commas;
+        self
+    }
+}
+
+impl Default for WriteOptions {
+    fn default() -> Self {
+        Self {
+            indent: String::new(),
+            unquoted_keys: false,
+            quote: '"',
+            trailing_commas: false,
+        }
+    }
+}
@@ -1,152 +1,903 @@
-use crate::error::Error;
+mod scan;
+
+use self::scan::{scan, Token, TokenKind};
+use crate::diagnostic::find_similar;
+use crate::error::{Error, Span};
+use crate::options::{Dialect, DuplicateKeys, Extension, ParseOptions, Root, Variables};
 use crate::tokenizer::{Rule, Tokenizer};
-use anyhow::Result;
+use crate::warning::Warning;
+use crate::Result;
 use pest::{iterators::Pair, iterators::Pairs, Parser};
 use serde_json::{map::Map, value::Value};
-use std::collections::{HashMap, VecDeque};
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
 use std::default::Default;
+use std::fmt;
+use std::io;
+use std::path::{Component, Path, PathBuf};
 use std::str::FromStr;
 
 #[derive(Debug)]
 pub struct Reference {
     target: String,
+    sources: Vec<Source>,
     value: Option<Value>,
-    location: String,
+    /// Byte offset of the reference, including its `&{`, in the parsed string.
+    offset: usize,
+    span: Span,
+}
+
+impl Reference {
+    /// The length of the reference as it is written in the string, e.g. `&{a.b}`.
+    fn placeholder_len(&self) -> usize {
+        self.target.len() + 3
+    }
+}
+
+/// Where the value of a reference comes from. A reference has at least one source and the
+/// following ones are fallbacks, tried in order, e.g. `&{a ?? env:B ?? 'c'}`.
+#[derive(Debug)]
+pub enum Source {
+    Path(Vec<Segment>),
+    Variable(String),
+    /// A file as it is written in the reference and a path into that file.
+    File(String, Vec<Segment>),
+    Literal(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Segment {
+    Key(String),
+    Index(i64),
+}
+
+impl fmt::Display for Segment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Segment::Key(key) => write!(f, "{}", key),
+            Segment::Index(index) => write!(f, "[{}]", index),
+        }
+    }
+}
+
+/// Everything besides the document itself that references can be resolved from. Files
+/// that don't exist are `None`.
+struct Sources<'a> {
+    variables: &'a dyn Variables,
+    files: HashMap<String, Option<Value>>,
+    /// Files that failed to load while recovering from errors, references to them have
+    /// already been reported.
+    failed: HashSet<String>,
 }
 
 #[derive(Default, Debug)]
 pub struct Context {
-    references: HashMap<String, Vec<Reference>>,
-    location: Vec<String>,
+    references: HashMap<Vec<Segment>, Vec<Reference>>,
+    /// The span of every object key, used to point out where a missing reference was expected.
+    keys: HashMap<Vec<Segment>, Span>,
+    /// The span of every value, used to point out where deserializing a value failed.
+    values: HashMap<Vec<Segment>, Span>,
+    location: Vec<Segment>,
+    errors: Errors,
+    warnings: Vec<Warning>,
+    dialect: Dialect,
+}
+
+/// Collects errors when recovering from them, otherwise the first error is returned as is.
+#[derive(Default, Debug)]
+struct Errors {
+    recover: bool,
+    errors: Vec<Error>,
+}
+
+impl Errors {
+    fn report(&mut self, error: Error) -> Result<()> {
+        if self.recover {
+            self.errors.push(error);
+            Ok(())
+        } else {
+            Err(error)
+        }
+    }
+
+    /// Returns every error that has been reported, ordered by where they occurred.
+    fn finish(&mut self) -> Result<()> {
+        let mut errors = std::mem::take(&mut self.errors);
+        errors.sort_by_key(|error| error.span().start);
+        match errors.len() {
+            0 => Ok(()),
+            1 => Err(errors.remove(0)),
+            _ => Err(Error::Multiple { errors }),
+        }
+    }
 }
 
 pub fn parse(input: &str) -> Result<Value> {
+    parse_with_options(input, &ParseOptions::default())
+}
+
+pub fn parse_with_options(input: &str, options: &ParseOptions) -> Result<Value> {
+    parse_with_warnings(input, options).map(|(value, _)| value)
+}
+
+/// Parses the input like [`parse_with_options`] and returns the warnings of the document
+/// alongside its value. Warnings of referenced files are not included.
+pub fn parse_with_warnings(input: &str, options: &ParseOptions) -> Result<(Value, Vec<Warning>)> {
+    parse_with_spans(input, options).map(|parsed| (parsed.value, parsed.warnings))
+}
+
+/// A parsed document along with where its keys and values are written. Values that were
+/// referenced are located at the reference.
+pub(crate) struct Parsed {
+    pub value: Value,
+    pub warnings: Vec<Warning>,
+    pub keys: HashMap<Vec<Segment>, Span>,
+    pub values: HashMap<Vec<Segment>, Span>,
+}
+
+pub(crate) fn parse_with_spans(input: &str, options: &ParseOptions) -> Result<Parsed> {
+    let mut imports = options.get_path().map(normalize_path).into_iter().collect();
+    parse_document(input, options, &mut imports)
+}
+
+/// Decodes an object key as it is written, e.g. `"a\&{b"` into `a&{b`.
+pub(crate) fn parse_key(key: &str, options: &ParseOptions) -> Result<String> {
+    let mut ctx = Context {
+        dialect: options.get_dialect().clone(),
+        ..Context::default()
+    };
+    let rule = if key.starts_with(['"', '\'']) {
+        Rule::string
+    } else {
+        Rule::identifier
+    };
+    let pair = Tokenizer::parse(rule, key)?
+        .next()
+        .expect("failed to parse the key");
+    match parse_string(pair, &mut ctx, false)? {
+        Value::String(key) => Ok(key),
+        _ => unreachable!("keys are always strings"),
+    }
+}
+
+/// Parses a document and the files it references. `imports` holds the files that are being
+/// parsed at the moment, the last one being the file of `input` itself.
+fn parse_document(
+    input: &str,
+    options: &ParseOptions,
+    imports: &mut Vec<PathBuf>,
+) -> Result<Parsed> {
     let mut ctx = Context::default();
+    ctx.errors.recover = options.get_recover();
+    ctx.dialect = options.get_dialect().clone();
 
-    let tokenizer = Tokenizer::parse(Rule::root, input)?
+    let input = recover_syntax_errors(input, &mut ctx.errors)?;
+    let tokenizer = Tokenizer::parse(Rule::root, &input)
+        .map_err(|error| get_syntax_error(&input, error))?
         .next()
         .expect("failed to parse the file");
-
-    let mut json = match tokenizer.as_rule() {
-        Rule::object => parse_object(tokenizer.into_inner(), &mut ctx)?,
-        Rule::array => parse_array(tokenizer.into_inner(), &mut ctx)?,
-        _ => unreachable!("json can only be of type array or object"),
+    check_comments(&input, &mut ctx)?;
+    let expected = match (ctx.dialect.root, tokenizer.as_rule()) {
+        (Root::Object, Rule::object) => None,
+        (Root::Object, _) => Some("Expected object"),
+        (Root::ObjectOrArray, Rule::object) | (Root::ObjectOrArray, Rule::array) => None,
+        (Root::ObjectOrArray, _) => Some("Expected object or array"),
+        (Root::Any, _) => None,
     };
+    if let Some(message) = expected {
+        ctx.errors.report(Error::Syntax {
+            message: message.to_string(),
+            span: Span::from_pest(tokenizer.as_span()),
+        })?;
+    }
+
+    let mut json = parse_value(tokenizer, &mut ctx)?;
 
-    get_reference_values(&json, &mut ctx)?;
-    set_reference_values(&mut json, &ctx)?;
+    let sources = load_files(&mut ctx, options, imports)?;
+    resolve_references(&mut json, &mut ctx, &sources)?;
+    ctx.errors.finish()?;
+    add_definition_warnings(&json, &mut ctx, &sources);
+    ctx.warnings.sort_by_key(|warning| warning.span().start);
 
-    Ok(json)
+    Ok(Parsed {
+        value: json,
+        warnings: ctx.warnings,
+        keys: ctx.keys,
+        values: ctx.values,
+    })
 }
 
-fn get_reference_values(data: &Value, ctx: &mut Context) -> Result<()> {
-    for (_target, references) in ctx.references.iter_mut() {
+/// Comments aren't part of the parsed tokens, so they are looked up separately when they
+/// aren't allowed.
+fn check_comments(input: &str, ctx: &mut Context) -> Result<()> {
+    for token in scan(input) {
+        let extension = match token.kind {
+            TokenKind::LineComment => Extension::LineComment,
+            TokenKind::BlockComment => Extension::BlockComment,
+            TokenKind::Separator(_) | TokenKind::String { .. } => continue,
+        };
+        check_extension(
+            extension,
+            Span::from_offsets(input, token.start, token.end),
+            ctx,
+        )?;
+    }
+    Ok(())
+}
+
+fn check_extension(extension: Extension, span: Span, ctx: &mut Context) -> Result<()> {
+    if ctx.dialect.allows(extension) {
+        Ok(())
+    } else {
+        ctx.errors.report(Error::Extension { extension, span })
+    }
+}
+
+/// Checks that there is no comma after the last value of an object or array, if that's not
+/// allowed.
+fn check_trailing_comma(pairs: &Pairs<Rule>, ctx: &mut Context) -> Result<()> {
+    match pairs.clone().last() {
+        Some(pair) if pair.as_rule() == Rule::comma => {
+            let span = Span::from_pest(pair.as_span());
+            check_extension(Extension::TrailingComma, span, ctx)
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Reports every syntax error when recovering from errors. After each error the input is
+/// blanked out up to the next `,`, `}` or `]`, so that parsing can continue from there. An
+/// error inside of a string only blanks out its content. Blanking keeps the offsets of
+/// everything that follows, and with that their spans. If the input ends too early, the
+/// string and brackets that are still open are closed once, errors in what has been added
+/// aren't reported as the end of the input has been reported already.
+fn recover_syntax_errors<'a>(input: &'a str, errors: &mut Errors) -> Result<Cow<'a, str>> {
+    if !errors.recover {
+        return Ok(Cow::Borrowed(input));
+    }
+    let end = input.trim_end().len();
+    let mut is_closed = false;
+    let mut input = Cow::Borrowed(input);
+    while let Err(error) = Tokenizer::parse(Rule::root, &input) {
+        let position = get_error_position(&error);
+        if position < end || !is_closed {
+            errors.report(get_syntax_error(&input, error))?;
+        }
+        if position >= end && !is_closed {
+            let closers = get_closers(&input);
+            input.to_mut().push_str(&closers);
+            is_closed = true;
+            continue;
+        }
+        let range = match get_string_at(&input, position) {
+            Some(Token {
+                kind: TokenKind::String { closed: true },
+                start,
+                end,
+            }) => start + 1..end - 1,
+            Some(token) => token.start + 1..token.end,
+            None => get_recovery_range(&input, position),
+        };
+        if input[range.clone()].trim().is_empty() {
+            // Nothing is left to skip, so the remaining errors can't be found
+            return Err(errors.finish().expect_err("an error has been reported"));
+        }
+        let blank = input[range.clone()]
+            .chars()
+            .map(|c| match c {
+                '\n' => "\n".to_string(),
+                _ => " ".repeat(c.len_utf8()),
+            })
+            .collect::<String>();
+        input.to_mut().replace_range(range, &blank);
+    }
+    Ok(input)
+}
+
+fn get_error_position(error: &pest::error::Error<Rule>) -> usize {
+    match error.location {
+        pest::error::InputLocation::Pos(position) => position,
+        pest::error::InputLocation::Span((start, _)) => start,
+    }
+}
+
+/// Names errors inside of strings by their cause, instead of listing everything a string
+/// could continue with.
+fn get_syntax_error(input: &str, error: pest::error::Error<Rule>) -> Error {
+    let position = get_error_position(&error);
+    match get_string_at(input, position) {
+        Some(token) if input[position..].starts_with('\\') => {
+            let escape = match input[position + 1..].chars().next() {
+                Some('u') => input[position..]
+                    .char_indices()
+                    .take_while(|(index, c)| *index < 2 || (*index < 6 && c.is_ascii_hexdigit()))
+                    .map(|(_, c)| c)
+                    .collect(),
+                Some(c) => format!("\\{}", c),
+                None => "\\".to_string(),
+            };
+            let end = (position + escape.len()).min(token.end);
+            Error::InvalidEscape {
+                escape,
+                span: Span::from_offsets(input, position, end),
+            }
+        }
+        Some(token) if token.kind == (TokenKind::String { closed: false }) => Error::Syntax {
+            message: "Unterminated string".to_string(),
+            span: Span::from_offsets(input, token.start, token.start + 1),
+        },
+        _ => error.into(),
+    }
+}
+
+/// The string that `position` is inside of, not counting its opening quote.
+fn get_string_at(input: &str, position: usize) -> Option<Token> {
+    scan(input).into_iter().find(|token| match token.kind {
+        TokenKind::String { closed } => {
+            token.start < position && (position < token.end - 1 || !closed)
+        }
+        _ => false,
+    })
+}
+
+/// What closes the string and brackets that are open at the end of the input.
+fn get_closers(input: &str) -> String {
+    let mut closers = Vec::new();
+    for token in scan(input) {
+        match token.kind {
+            TokenKind::Separator('{') => closers.push('}'),
+            TokenKind::Separator('[') => closers.push(']'),
+            TokenKind::Separator('}') | TokenKind::Separator(']') => {
+                closers.pop();
+            }
+            TokenKind::String { closed: false } => {
+                closers.extend(input[token.start..].chars().next());
+            }
+            _ => {}
+        }
+    }
+    closers.into_iter().rev().collect()
+}
+
+/// The part of the input to skip after a syntax error at `position`. That's everything up
+/// to the next separator, or the whole member or value it's in if skipping less would
+/// leave an empty value behind, which would be reported again.
+fn get_recovery_range(input: &str, position: usize) -> std::ops::Range<usize> {
+    let separators = scan(input)
+        .into_iter()
+        .filter_map(|token| match token.kind {
+            TokenKind::Separator(c) => Some((token.start, c)),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    let end = separators
+        .iter()
+        .find(|(index, c)| *index >= position && matches!(c, ',' | '}' | ']'))
+        .map_or(input.len(), |(index, _)| *index);
+    let start = separators
+        .iter()
+        .rev()
+        .find(|(index, c)| *index < position && matches!(c, ',' | '{' | '['))
+        .map_or(0, |(index, _)| index + 1);
+    let before = input[start..position].trim_end();
+    if !input[position..end].trim().is_empty() && !before.is_empty() && !before.ends_with(':') {
+        return position..end;
+    }
+    if input[end..].starts_with(',') {
+        start..end + 1
+    } else {
+        start..end
+    }
+}
+
+/// Parses every file that is referenced by the document. Paths are relative to the file
+/// that references them, or to the current directory if the path of the document is unknown.
+fn load_files<'a>(
+    ctx: &mut Context,
+    options: &'a ParseOptions,
+    imports: &mut Vec<PathBuf>,
+) -> Result<Sources<'a>> {
+    let base = imports
+        .last()
+        .and_then(|path| path.parent())
+        .map(Path::to_path_buf)
+        .unwrap_or_default();
+    let mut files = HashMap::new();
+    let mut failed = HashSet::new();
+    for reference in ctx.references.values().flatten() {
+        for source in &reference.sources {
+            let name = match source {
+                Source::File(name, _) if !files.contains_key(name) && !failed.contains(name) => {
+                    name
+                }
+                _ => continue,
+            };
+            let path = normalize_path(&base.join(name));
+            if let Some(start) = imports.iter().position(|x| *x == path) {
+                let mut cycle = imports[start..]
+                    .iter()
+                    .map(|path| path.display().to_string())
+                    .collect::<Vec<_>>();
+                cycle.push(path.display().to_string());
+                ctx.errors.report(Error::CyclicImport {
+                    cycle,
+                    span: reference.span,
+                })?;
+                failed.insert(name.clone());
+                continue;
+            }
+            let error = match options.get_files().read(&path) {
+                Ok(input) => {
+                    imports.push(path.clone());
+                    let value = parse_document(&input, options, imports);
+                    imports.pop();
+                    match value {
+                        Ok(parsed) => {
+                            files.insert(name.clone(), Some(parsed.value));
+                            continue;
+                        }
+                        Err(error @ Error::CyclicImport { .. }) => error,
+                        Err(error) => Error::Import {
+                            path: path.display().to_string(),
+                            span: reference.span,
+                            source: Box::new(error),
+                        },
+                    }
+                }
+                Err(error) if error.kind() == io::ErrorKind::NotFound => {
+                    files.insert(name.clone(), None);
+                    continue;
+                }
+                Err(error) => Error::Io {
+                    path: path.display().to_string(),
+                    message: error.to_string(),
+                    span: reference.span,
+                },
+            };
+            ctx.errors.report(error)?;
+            failed.insert(name.clone());
+        }
+    }
+    Ok(Sources {
+        variables: options.get_variables(),
+        files,
+        failed,
+    })
+}
+
+/// Removes `.` and resolves `..` without touching the file system, so that the same file
+/// always gets the same path.
+fn normalize_path(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if !normalized.pop() {
+                    normalized.push(component);
+                }
+            }
+            _ => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+/// Resolves the references location by location, making sure that every location a
+/// reference depends on has been resolved before the reference itself is read.
+fn resolve_references(data: &mut Value, ctx: &mut Context, sources: &Sources) -> Result<()> {
+    let locations = sort_reference_locations(data, &ctx.references, &mut ctx.errors, sources)?;
+    for location in locations {
+        let references = ctx
+            .references
+            .get_mut(&location)
+            .expect("sorted locations should exist");
         for reference in references.iter_mut() {
-            let value = get_reference_value(data, &reference, &reference.target)?;
-            reference.value = Some(value);
+            match get_reference_value(data, reference, sources, &ctx.keys) {
+                Ok(Value::String(value)) if value.is_empty() => {
+                    ctx.warnings.push(Warning::EmptyReference {
+                        target: reference.target.clone(),
+                        span: reference.span,
+                    });
+                    reference.value = Some(Value::String(value));
+                }
+                Ok(value) => reference.value = Some(value),
+                Err(_) if is_from_failed_file(reference, sources) => {}
+                Err(error) => ctx.errors.report(error)?,
+            }
         }
+        set_reference_value_at_target(data, &location, references, &mut ctx.errors)?;
     }
     Ok(())
 }
 
-fn get_reference_value(data: &Value, reference: &Reference, path: &str) -> Result<Value> {
-    let mut keys = path.split('.').collect::<Vec<_>>();
-    let first_key = keys.first().expect("should have a key");
-    if let Some(value) = data.get(&first_key) {
-        let value = match value {
-            Value::Object(_) => {
-                keys.remove(0);
-                get_reference_value(&value, reference, keys.join(".").as_str())?
+/// Warns about keys that can't be referenced since they are shadowed by a path, and about
+/// values that are never referenced although their siblings are.
+fn add_definition_warnings(data: &Value, ctx: &mut Context, sources: &Sources) {
+    let targets = ctx
+        .references
+        .values()
+        .flatten()
+        .filter_map(|reference| get_reference_target(data, reference, sources))
+        .collect::<Vec<_>>();
+    let mut locations = ctx.keys.iter().collect::<Vec<_>>();
+    locations.sort_by_key(|(location, _)| *location);
+    for (location, span) in locations {
+        let (key, parent) = match location.split_last() {
+            Some((Segment::Key(key), parent)) => (key, parent),
+            _ => continue,
+        };
+        if key.contains('.') {
+            let mut path = parent.to_vec();
+            path.extend(key.split('.').map(|key| Segment::Key(key.to_string())));
+            if get_path_value(data, &path).is_ok() {
+                ctx.warnings.push(Warning::ShadowedKey {
+                    key: key.clone(),
+                    span: *span,
+                });
             }
-            Value::Array(_) => {
-                return Err(Error::Parsing(format!(
-                    "Referencing arrays are not supported, failed at key: {}",
-                    &first_key
-                ))
-                .into())
+        }
+        // The root object holds everything, so it isn't considered a set of definitions
+        let is_definition = !parent.is_empty()
+            && targets
+                .iter()
+                .any(|target| target.len() > parent.len() && target.starts_with(parent));
+        let is_referenced = targets.iter().any(|target| {
+            let length = target.len().min(location.len());
+            target[..length] == location[..length]
+        });
+        if is_definition && !is_referenced {
+            ctx.warnings.push(Warning::UnusedDefinition {
+                path: format_location(location),
+                span: *span,
+            });
+        }
+    }
+}
+
+/// Whether the reference can't be resolved because its file has failed to load, which has
+/// already been reported.
+fn is_from_failed_file(reference: &Reference, sources: &Sources) -> bool {
+    match &reference.sources[0] {
+        Source::File(name, _) => sources.failed.contains(name),
+        _ => false,
+    }
+}
+
+/// Orders the locations that hold references so that dependencies come first. A location
+/// depends on another when the target of one of its references contains, or is contained
+/// by, the other location.
+fn sort_reference_locations(
+    data: &Value,
+    references: &HashMap<Vec<Segment>, Vec<Reference>>,
+    errors: &mut Errors,
+    sources: &Sources,
+) -> Result<Vec<Vec<Segment>>> {
+    let mut locations = references.keys().collect::<Vec<_>>();
+    locations.sort();
+
+    let mut dependencies = HashMap::new();
+    for (location, location_references) in references.iter() {
+        let mut depends_on = Vec::new();
+        for reference in location_references {
+            let target = match get_reference_target(data, reference, sources) {
+                Some(target) => target,
+                None => continue,
+            };
+            for other in locations.iter() {
+                let length = target.len().min(other.len());
+                if target[..length] == other[..length] {
+                    depends_on.push(*other);
+                }
             }
-            _ => value.clone(),
+        }
+        dependencies.insert(location, depends_on);
+    }
+
+    let mut sorted = Vec::new();
+    let mut visiting = Vec::new();
+    for location in locations {
+        let mut visit = Visit {
+            references,
+            dependencies: &dependencies,
+            visiting: &mut visiting,
+            sorted: &mut sorted,
+            errors: &mut *errors,
         };
-        Ok(value)
-    } else {
-        Err(Error::Parsing(format!(
-            "No data was found in: {} at {}",
-            reference.target, first_key
-        ))
-        .into())
+        visit.visit(location)?;
     }
+    Ok(sorted.into_iter().cloned().collect())
+}
+
+/// The state of the depth first search in `sort_reference_locations`.
+struct Visit<'a, 'b> {
+    references: &'a HashMap<Vec<Segment>, Vec<Reference>>,
+    dependencies: &'b HashMap<&'a Vec<Segment>, Vec<&'a Vec<Segment>>>,
+    visiting: &'b mut Vec<&'a Vec<Segment>>,
+    sorted: &'b mut Vec<&'a Vec<Segment>>,
+    errors: &'b mut Errors,
 }
 
-fn set_reference_values(data: &mut Value, ctx: &Context) -> Result<()> {
-    for (target, references) in ctx.references.iter() {
-        let mut path = target.split('.').collect::<VecDeque<_>>();
-        set_reference_value_at_target(data, &mut path, references, true)?;
+impl<'a, 'b> Visit<'a, 'b> {
+    fn visit(&mut self, location: &'a Vec<Segment>) -> Result<()> {
+        if self.sorted.contains(&location) {
+            return Ok(());
+        }
+        if let Some(start) = self.visiting.iter().position(|x| *x == location) {
+            let mut cycle = self.visiting[start..]
+                .iter()
+                .map(|location| format_location(location))
+                .collect::<Vec<_>>();
+            cycle.push(format_location(location));
+            // When recovering the cycle is broken up here, its references stay unresolved
+            return self.errors.report(Error::CyclicReference {
+                cycle,
+                span: self.references[location][0].span,
+            });
+        }
+        self.visiting.push(location);
+        for dependency in &self.dependencies[location] {
+            self.visit(dependency)?;
+        }
+        self.visiting.pop();
+        self.sorted.push(location);
+        Ok(())
     }
-    Ok(())
 }
 
-fn set_reference_value_at_target(
-    data: &mut Value,
-    path: &mut VecDeque<&str>,
-    references: &[Reference],
-    hard_error: bool,
-) -> Result<()> {
-    let key = path.pop_front();
-    if let Some(key) = key {
-        match data {
-            Value::Array(array) => {
-                for value in array {
-                    path.push_front(key);
-                    set_reference_value_at_target(value, path, references, false)?;
+/// Returns the location of the first path that will be used to resolve the reference, if
+/// the reference will be resolved from a path at all.
+fn get_reference_target(
+    data: &Value,
+    reference: &Reference,
+    sources: &Sources,
+) -> Option<Vec<Segment>> {
+    for source in &reference.sources {
+        match source {
+            Source::Path(path) => {
+                if let Some(target) = get_target_location(data, path) {
+                    return Some(target);
                 }
             }
-            Value::Object(_) => {
-                if let Some(data) = data.get_mut(key) {
-                    set_reference_value_at_target(data, path, references, true)?;
-                } else {
-                    // TODO: This is to prevent errors when looping over multiple elements inside of an
-                    // array (logic above). We should probably improve this so that we index into the
-                    // array instead of looping over every element.
-                    if hard_error {
-                        return Err(Error::Parsing(format!("unknown reference key {}", key)).into());
-                    }
+            Source::Variable(name) => {
+                if sources.variables.get(name).is_some() {
+                    return None;
+                }
+            }
+            Source::File(name, path) => {
+                if get_file_value(sources, name, path).is_ok() {
+                    return None;
+                }
+            }
+            Source::Literal(_) => return None,
+        }
+    }
+    None
+}
+
+/// Walks along the path of the reference and returns the keys and indices that were
+/// passed, which is the same format as the locations of the references. The walk stops
+/// early at strings since they may hold references that resolve into objects or arrays,
+/// `None` is returned when the target doesn't exist at all.
+fn get_target_location(data: &Value, path: &[Segment]) -> Option<Vec<Segment>> {
+    let mut location = Vec::new();
+    let mut value = data;
+    for segment in path {
+        value = match (value, segment) {
+            (Value::Object(object), Segment::Key(key)) => {
+                location.push(Segment::Key(key.clone()));
+                object.get(key)?
+            }
+            (Value::Array(array), Segment::Index(index)) => {
+                let index = get_array_index(array, *index)?;
+                location.push(Segment::Index(index as i64));
+                &array[index]
+            }
+            (Value::Array(array), Segment::Key(key)) => {
+                let index = get_array_index(array, key.parse().ok()?)?;
+                location.push(Segment::Index(index as i64));
+                &array[index]
+            }
+            (Value::String(_), _) => break,
+            _ => return None,
+        };
+    }
+    Some(location)
+}
+
+fn get_reference_value(
+    data: &Value,
+    reference: &Reference,
+    sources: &Sources,
+    keys: &HashMap<Vec<Segment>, Span>,
+) -> Result<Value> {
+    for source in &reference.sources {
+        match source {
+            Source::Path(path) => {
+                if let Ok(value) = get_path_value(data, path) {
+                    return Ok(value.clone());
+                }
+            }
+            Source::Variable(name) => {
+                if let Some(value) = sources.variables.get(name) {
+                    return Ok(Value::String(value));
+                }
+            }
+            Source::File(name, path) => {
+                if let Ok(value) = get_file_value(sources, name, path) {
+                    return Ok(value.clone());
                 }
             }
-            _ => unimplemented!("only objects can have references"),
+            Source::Literal(literal) => return Ok(Value::String(literal.clone())),
+        }
+    }
+    // Nothing could be resolved, the error is about the first source of the reference.
+    let (missing, parent, suggestion) = match &reference.sources[0] {
+        Source::Path(path) => {
+            let index = get_path_value(data, path).expect_err("path should be missing");
+            let suggestion = suggest_path(data, path, index).map(|path| format_location(&path));
+            (
+                path[index].to_string(),
+                keys.get(&path[..index]).copied().map(Box::new),
+                suggestion,
+            )
         }
+        Source::Variable(name) => (name.clone(), None, None),
+        Source::File(name, path) => match get_file_value(sources, name, path) {
+            Err(Some(index)) => {
+                let suggestion = match sources.files.get(name) {
+                    Some(Some(file)) => suggest_path(file, path, index)
+                        .map(|path| format!("file:{}#{}", name, format_location(&path))),
+                    _ => None,
+                };
+                (path[index].to_string(), None, suggestion)
+            }
+            _ => (name.clone(), None, None),
+        },
+        Source::Literal(_) => unreachable!("literals can always be resolved"),
+    };
+    Err(Error::UnresolvedReference {
+        target: reference.target.clone(),
+        missing,
+        span: reference.span,
+        parent,
+        suggestion,
+    })
+}
+
+/// Replaces the missing key of a path with a similar key that does exist, e.g. `color.red`
+/// for `color.rde`.
+fn suggest_path(data: &Value, path: &[Segment], index: usize) -> Option<Vec<Segment>> {
+    let key = match &path[index] {
+        Segment::Key(key) => key,
+        Segment::Index(_) => return None,
+    };
+    let object = get_path_value(data, &path[..index]).ok()?.as_object()?;
+    let similar = find_similar(key, object.keys().map(String::as_str))?;
+    let mut path = path[..index].to_vec();
+    path.push(Segment::Key(similar.to_string()));
+    Some(path)
+}
+
+/// Returns the value at the path in a referenced file. The error holds the index of the
+/// segment that couldn't be found, or `None` if the file doesn't exist.
+fn get_file_value<'a>(
+    sources: &'a Sources,
+    name: &str,
+    path: &[Segment],
+) -> Result<&'a Value, Option<usize>> {
+    match sources.files.get(name) {
+        Some(Some(data)) => get_path_value(data, path).map_err(Some),
+        _ => Err(None),
+    }
+}
+
+/// Returns the value at the path or the index of the segment that couldn't be found.
+fn get_path_value<'a>(data: &'a Value, path: &[Segment]) -> Result<&'a Value, usize> {
+    let mut value = data;
+    for (index, segment) in path.iter().enumerate() {
+        let next = match (value, segment) {
+            (Value::Object(object), Segment::Key(key)) => object.get(key),
+            (Value::Array(array), Segment::Index(index)) => {
+                get_array_index(array, *index).map(|index| &array[index])
+            }
+            (Value::Array(array), Segment::Key(key)) => key
+                .parse::<i64>()
+                .ok()
+                .and_then(|index| get_array_index(array, index))
+                .map(|index| &array[index]),
+            _ => None,
+        };
+        value = next.ok_or(index)?;
+    }
+    Ok(value)
+}
+
+/// Negative indices count from the end of the array, `-1` being the last element.
+fn get_array_index(array: &[Value], index: i64) -> Option<usize> {
+    let index = if index < 0 {
+        array.len() as i64 + index
+    } else {
+        index
+    };
+    if index < 0 || index as usize >= array.len() {
+        None
     } else {
-        for reference in references {
+        Some(index as usize)
+    }
+}
+
+fn set_reference_value_at_target(
+    data: &mut Value,
+    location: &[Segment],
+    references: &[Reference],
+    errors: &mut Errors,
+) -> Result<()> {
+    let mut data = data;
+    for segment in location {
+        let next = match (data, segment) {
+            (Value::Object(object), Segment::Key(key)) => object.get_mut(key),
+            (Value::Array(array), Segment::Index(index)) => array.get_mut(*index as usize),
+            _ => None,
+        };
+        data = next.ok_or_else(|| Error::InvalidReference {
+            message: format!("Unknown reference location {}", format_location(location)),
+            span: references[0].span,
+        })?;
+    }
+    // A string that consists of nothing but a reference takes over the referenced value
+    // as is, which keeps numbers, objects etc. typed.
+    if let ([reference], Value::String(string)) = (references, &data) {
+        if reference.offset == 0 && reference.placeholder_len() == string.len() {
             if let Some(value) = &reference.value {
-                match (&data, value) {
-                    (Value::String(x), Value::String(ref y)) => {
-                        let new_value = x
-                            .clone()
-                            .replace(format!("&{{{}}}", &reference.target).as_str(), &y);
-                        *data = Value::String(new_value);
-                    }
-                    _ => {
-                        return Err(Error::Parsing(
-                            "only string references have been implemented".to_string(),
-                        )
-                        .into());
-                    }
-                };
-            } else {
-                println!(
-                    "{}",
-                    format!("reference {} doesnt have a value", reference.target)
-                );
+                *data = value.clone();
+                return Ok(());
+            }
+        }
+    }
+    if let Value::String(string) = data {
+        // The references are replaced from the back so that the offsets of the remaining
+        // references stay valid.
+        for reference in references.iter().rev() {
+            let range = reference.offset..reference.offset + reference.placeholder_len();
+            match &reference.value {
+                Some(Value::String(value)) => string.replace_range(range, value),
+                Some(_) => errors.report(Error::InvalidReference {
+                    message: "Only string references can be part of a larger string".to_string(),
+                    span: reference.span,
+                })?,
+                // The reference couldn't be resolved, which has already been reported
+                None => {}
             }
         }
     }
     Ok(())
 }
 
+/// Formats a location the same way as it would be written in a reference, e.g. `a.b[0].c`.
+fn format_location(location: &[Segment]) -> String {
+    let mut string = String::new();
+    for segment in location {
+        if let Segment::Key(_) = segment {
+            if !string.is_empty() {
+                string.push('.');
+            }
+        }
+        string.push_str(&segment.to_string());
+    }
+    string
+}
+
 fn parse_value(pair: Pair<Rule>, ctx: &mut Context) -> Result<Value> {
+    let span = Span::from_pest(pair.as_span());
+    ctx.values.insert(ctx.location.clone(), span);
     match pair.as_rule() {
         Rule::null => Ok(Value::Null),
-        Rule::bool => parse_bool(pair.as_str()),
-        Rule::number => parse_number(pair.as_str()),
+        Rule::bool => Ok(Value::Bool(pair.as_str() == "true")),
+        Rule::number => parse_number(pair).or_else(|error| {
+            ctx.errors.report(error)?;
+            Ok(Value::Null)
+        }),
         Rule::string => parse_string(pair, ctx, true),
         Rule::object => parse_object(pair.into_inner(), ctx),
         Rule::array => parse_array(pair.into_inner(), ctx),
@@ -154,49 +905,70 @@ fn parse_value(pair: Pair<Rule>, ctx: &mut Context) -> Result<Value> {
     }
 }
 
-fn parse_bool(value: &str) -> Result<Value> {
-    Ok(Value::from_str(value)?)
-}
-
-fn parse_number(value: &str) -> Result<Value> {
-    Ok(Value::from_str(value)?)
+fn parse_number(pair: Pair<Rule>) -> Result<Value> {
+    Value::from_str(pair.as_str()).map_err(|_| Error::InvalidNumber {
+        number: pair.as_str().to_string(),
+        span: Span::from_pest(pair.as_span()),
+    })
 }
 
 fn parse_array(pairs: Pairs<Rule>, ctx: &mut Context) -> Result<Value> {
+    check_trailing_comma(&pairs, ctx)?;
     let mut array = Vec::new();
-    for pair in pairs {
+    let values = pairs.filter(|pair| pair.as_rule() != Rule::comma);
+    for (index, pair) in values.enumerate() {
+        ctx.location.push(Segment::Index(index as i64));
         let value = parse_value(pair, ctx)?;
         array.push(value);
+        ctx.location.pop();
     }
     Ok(Value::Array(array))
 }
 
 fn parse_object(pairs: Pairs<Rule>, ctx: &mut Context) -> Result<Value> {
+    check_trailing_comma(&pairs, ctx)?;
     let mut object = Map::new();
-    for pair in pairs {
-        // TODO: Improve this key value logic
-        let mut key_value_pair = Vec::new();
-        for (index, key_value) in pair.into_inner().enumerate() {
-            let value = match index {
-                0 => {
-                    let key = parse_string(key_value, ctx, false)?;
-                    ctx.location.push(key.clone().as_str().unwrap().to_string()); // TODO: Use str
-                    key
-                }
-                1 => parse_value(key_value, ctx)?,
-                _ => unreachable!("pair should only be two"),
-            };
-            key_value_pair.push(value);
+    for pair in pairs.filter(|pair| pair.as_rule() == Rule::pair) {
+        let mut pairs = pair
+            .into_inner()
+            .filter(|pair| pair.as_rule() != Rule::colon);
+        let key_pair = pairs.next().expect("pair should have a key");
+        let value_pair = pairs.next().expect("pair should have a value");
+        let key_span = Span::from_pest(key_pair.as_span());
+        let key = match parse_string(key_pair, ctx, false)? {
+            Value::String(key) => key,
+            _ => unreachable!("keys are always strings"),
+        };
+        ctx.location.push(Segment::Key(key.clone()));
+        let is_duplicate = object.contains_key(&key);
+        if is_duplicate && ctx.dialect.duplicate_keys == DuplicateKeys::Error {
+            // When recovering the first value is kept
+            ctx.errors.report(Error::DuplicateKey {
+                key: key.clone(),
+                span: key_span,
+            })?;
         }
-        let key = key_value_pair[0]
-            .clone()
-            .as_str()
-            .expect("failed to translate value to str")
-            .to_string();
-        if object.contains_key(&key) {
-            return Err(Error::Parsing(format!("Object already contains key: {}", key)).into());
+        if !is_duplicate || ctx.dialect.duplicate_keys == DuplicateKeys::Last {
+            if is_duplicate {
+                // The references and keys of the replaced value are gone along with it
+                let location = &ctx.location;
+                ctx.references.retain(|x, _| !x.starts_with(location));
+                ctx.keys.retain(|x, _| !x.starts_with(location));
+                ctx.values.retain(|x, _| !x.starts_with(location));
+            }
+            ctx.keys.insert(ctx.location.clone(), key_span);
+            let value = parse_value(value_pair, ctx)?;
+            object.insert(key, value);
         } else {
-            object.insert(key, key_value_pair[1].clone());
+            // The value is skipped, but still checked for errors
+            let references = std::mem::take(&mut ctx.references);
+            let keys = std::mem::take(&mut ctx.keys);
+            let values = std::mem::take(&mut ctx.values);
+            let value = parse_value(value_pair, ctx);
+            ctx.references = references;
+            ctx.keys = keys;
+            ctx.values = values;
+            value?;
         }
         ctx.location.pop();
     }
@@ -204,21 +976,48 @@ fn parse_object(pairs: Pairs<Rule>, ctx: &mut Context) -> Result<Value> {
 }
 
 fn parse_string(pair: Pair<Rule>, ctx: &mut Context, extract_refs: bool) -> Result<Value> {
-    let mut string = pair.as_str().to_string();
-    if string.starts_with("\"") || string.starts_with("\'") {
-        remove_wrapping_quotes(&mut string);
+    let span = Span::from_pest(pair.as_span());
+    if pair.as_rule() == Rule::identifier {
+        check_extension(Extension::UnquotedKey, span, ctx)?;
+        return Ok(Value::String(pair.as_str().to_string()));
+    }
+    if pair.as_str().starts_with('\'') {
+        check_extension(Extension::SingleQuotedString, span, ctx)?;
     }
+    let references = ctx.dialect.allows(Extension::Reference);
+    let mut string = String::new();
     for pair in pair.into_inner() {
         match pair.as_rule() {
-            Rule::text => replace_escape_in_string_pair(pair, &mut string)?,
+            Rule::text => replace_escape_in_string_pair(pair, &mut string, ctx)?,
+            Rule::reference | Rule::reference_start if !references => {
+                // Without references the text is kept as it is written
+                let span = pair.as_span();
+                match pair.as_rule() {
+                    Rule::reference => string.push_str(&format!("&{{{}}}", span.as_str())),
+                    _ => string.push_str(span.as_str()),
+                }
+            }
+            Rule::reference_start => {
+                ctx.errors.report(Error::InvalidReference {
+                    message: "Invalid reference".to_string(),
+                    span: Span::from_pest(pair.as_span()),
+                })?;
+                string.push_str(pair.as_str());
+            }
             Rule::reference => {
-                if extract_refs {
-                    add_reference_to_ctx(pair, ctx)?
+                // The reference is kept in the string until it gets resolved
+                let offset = string.len();
+                string.push_str(&format!("&{{{}}}", pair.as_str()));
+                let result = if extract_refs {
+                    add_reference_to_ctx(pair, ctx, offset)
                 } else {
-                    return Err(Error::Parsing(
-                        "References are not allowed inside of keys".to_string(),
-                    )
-                    .into());
+                    Err(Error::InvalidReference {
+                        message: "References are not allowed inside of keys".to_string(),
+                        span: get_reference_span(&pair),
+                    })
+                };
+                if let Err(error) = result {
+                    ctx.errors.report(error)?;
                 }
             }
             _ => unreachable!("strings can only consist of a text and/or a reference"),
@@ -227,53 +1026,208 @@ fn parse_string(pair: Pair<Rule>, ctx: &mut Context, extract_refs: bool) -> Resu
     Ok(Value::String(string))
 }
 
-fn replace_escape_in_string_pair(pair: Pair<Rule>, string: &mut String) -> Result<()> {
-    for pair in pair.into_inner() {
+fn replace_escape_in_string_pair(
+    pair: Pair<Rule>,
+    string: &mut String,
+    ctx: &mut Context,
+) -> Result<()> {
+    let text = pair.as_str();
+    let start = pair.as_span().start();
+    let text_span = Span::from_pest(pair.as_span());
+    if !ctx.dialect.allows(Extension::ControlCharacter) {
+        let escapes = pair.clone().into_inner().map(|pair| {
+            let span = pair.as_span();
+            span.start() - start..span.end() - start
+        });
+        let escapes = escapes.collect::<Vec<_>>();
+        for (index, c) in text.char_indices() {
+            if c < ' ' && !escapes.iter().any(|range| range.contains(&index)) {
+                let span = get_inner_span(text_span, text, index, index + c.len_utf8());
+                check_extension(Extension::ControlCharacter, span, ctx)?;
+            }
+        }
+    }
+    let mut last = 0;
+    let mut pairs = pair.into_inner().peekable();
+    while let Some(pair) = pairs.next() {
+        let mut end = pair.as_span().end();
         let new_value = match pair.as_rule() {
             Rule::esc_slash => '/'.to_string(),
             Rule::esc_backslash => '\\'.to_string(),
             Rule::esc_carriage_return => '\r'.to_string(),
             Rule::esc_tab => '\t'.to_string(),
             Rule::esc_quote_double => '\"'.to_string(),
-            Rule::esc_quote_single => '\''.to_string(),
+            Rule::esc_quote_single => {
+                let span = Span::from_pest(pair.as_span());
+                check_extension(Extension::EscapedSingleQuote, span, ctx)?;
+                '\''.to_string()
+            }
             Rule::esc_backspace => '\u{8}'.to_string(),
             Rule::esc_form_feed => '\u{c}'.to_string(),
             Rule::esc_new_line => '\n'.to_string(),
-            Rule::esc_unicode => parse_unicode(pair.as_str())?,
-            _ => unimplemented!(),
+            Rule::esc_unicode => match parse_unicode(&pair, pairs.peek()) {
+                Ok((unicode, false)) => unicode,
+                Ok((unicode, true)) => {
+                    end = pairs.next().expect("the low surrogate").as_span().end();
+                    unicode
+                }
+                Err(error) => {
+                    ctx.errors.report(error)?;
+                    pair.as_str().to_string()
+                }
+            },
+            Rule::esc_reference => {
+                let span = Span::from_pest(pair.as_span());
+                check_extension(Extension::Reference, span, ctx)?;
+                "&{".to_string()
+            }
+            _ => unreachable!("text can only consist of characters and escapes"),
         };
-        *string = string.replacen(&pair.as_str(), &new_value, 1);
+        string.push_str(&text[last..pair.as_span().start() - start]);
+        string.push_str(&new_value);
+        last = end - start;
     }
+    string.push_str(&text[last..]);
     Ok(())
 }
 
-fn parse_unicode(string: &str) -> Result<String> {
-    let unicode = &string[2..];
-    if let Some(unicode) = u32::from_str_radix(unicode, 16)
-        .ok()
-        .and_then(std::char::from_u32)
-    {
-        Ok(unicode.to_string())
-    } else {
-        Err(Error::Parsing(format!("unknown unicode {}", unicode)).into())
+/// The span of `text[start..end]`, `span` being the span of `text`.
+fn get_inner_span(span: Span, text: &str, start: usize, end: usize) -> Span {
+    let before = &text[..start];
+    let column = match before.rfind('\n') {
+        Some(index) => before[index + 1..].chars().count() + 1,
+        None => span.column + before.chars().count(),
+    };
+    Span {
+        start: span.start + start,
+        end: span.start + end,
+        line: span.line + before.matches('\n').count(),
+        column,
     }
 }
 
-fn add_reference_to_ctx(pair: Pair<Rule>, ctx: &mut Context) -> Result<()> {
-    let current_location = ctx.location.clone().join(".");
-    let entry = ctx
-        .references
-        .entry(current_location.clone())
-        .or_insert_with(Vec::new);
+/// Decodes a `\uXXXX` escape. A high surrogate is combined with a low surrogate that is
+/// escaped right after it, like in JSON, which is returned as `true` as it's used up.
+fn parse_unicode(pair: &Pair<Rule>, next: Option<&Pair<Rule>>) -> Result<(String, bool)> {
+    let code = |pair: &Pair<Rule>| u32::from_str_radix(&pair.as_str()[2..], 16).ok();
+    let low = next
+        .filter(|next| next.as_rule() == Rule::esc_unicode)
+        .filter(|next| next.as_span().start() == pair.as_span().end())
+        .and_then(code);
+    match (code(pair), low) {
+        (Some(high @ 0xD800..=0xDBFF), Some(low @ 0xDC00..=0xDFFF)) => {
+            let code = 0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00);
+            let unicode = std::char::from_u32(code).expect("surrogate pairs are valid");
+            Ok((unicode.to_string(), true))
+        }
+        (code, _) => code
+            .and_then(std::char::from_u32)
+            .map(|unicode| (unicode.to_string(), false))
+            .ok_or_else(|| Error::InvalidEscape {
+                escape: pair.as_str().to_string(),
+                span: Span::from_pest(pair.as_span()),
+            }),
+    }
+}
+
+fn add_reference_to_ctx(pair: Pair<Rule>, ctx: &mut Context, offset: usize) -> Result<()> {
+    let current_location = ctx.location.clone();
+    let target = pair.as_str().to_string();
+    let span = get_reference_span(&pair);
+    let mut sources = Vec::new();
+    for pair in pair.into_inner() {
+        let source = match pair.as_rule() {
+            // A string at the root has nothing to reference but itself
+            Rule::reference_path if current_location.is_empty() => {
+                return Err(Error::InvalidReference {
+                    message: format!("Reference {} needs an object or array at the root", target),
+                    span,
+                });
+            }
+            Rule::reference_path => Source::Path(parse_reference_path(pair, ctx, span)?),
+            Rule::reference_variable => Source::Variable(pair.as_str().to_string()),
+            Rule::reference_file => {
+                let mut pairs = pair.into_inner();
+                let name = pairs.next().expect("file should have a name");
+                let path = pairs.map(parse_reference_segment).collect::<Result<_>>()?;
+                Source::File(name.as_str().to_string(), path)
+            }
+            Rule::reference_literal | Rule::reference_default => {
+                Source::Literal(pair.as_str().to_string())
+            }
+            _ => {
+                unreachable!("references can only consist of paths, variables, files and literals")
+            }
+        };
+        sources.push(source);
+    }
+    let entry = ctx.references.entry(current_location).or_default();
     entry.push(Reference {
-        target: pair.as_str().to_string(),
+        target,
+        sources,
         value: None,
-        location: current_location,
+        offset,
+        span,
     });
     Ok(())
 }
 
-fn remove_wrapping_quotes(string: &mut String) {
-    string.remove(0);
-    string.pop();
+/// The span of a reference including its surrounding `&{` and `}`.
+fn get_reference_span(pair: &Pair<Rule>) -> Span {
+    let span = Span::from_pest(pair.as_span());
+    Span {
+        start: span.start - 2,
+        end: span.end + 1,
+        line: span.line,
+        column: span.column - 2,
+    }
+}
+
+fn parse_reference_path(pair: Pair<Rule>, ctx: &Context, span: Span) -> Result<Vec<Segment>> {
+    let target = pair.as_str();
+    let mut path = Vec::new();
+    for pair in pair.into_inner() {
+        if pair.as_rule() == Rule::reference_scope {
+            path = get_reference_scope(pair.as_str(), target, ctx, span)?;
+        } else {
+            path.push(parse_reference_segment(pair)?);
+        }
+    }
+    Ok(path)
+}
+
+fn parse_reference_segment(pair: Pair<Rule>) -> Result<Segment> {
+    match pair.as_rule() {
+        Rule::reference_key => Ok(Segment::Key(pair.as_str().to_string())),
+        Rule::reference_index => Ok(Segment::Index(pair.as_str().parse().map_err(|_| {
+            Error::InvalidReference {
+                message: format!("Invalid array index {}", pair.as_str()),
+                span: Span::from_pest(pair.as_span()),
+            }
+        })?)),
+        _ => unreachable!("references can only consist of keys and indices"),
+    }
+}
+
+/// Relative references start from the object or array that holds the referencing value. A
+/// single `.` stays in there while every additional `.`, or every `^.`, moves up one level.
+fn get_reference_scope(
+    scope: &str,
+    target: &str,
+    ctx: &Context,
+    span: Span,
+) -> Result<Vec<Segment>> {
+    let levels = if scope.starts_with('^') {
+        scope.matches('^').count()
+    } else {
+        scope.len() - 1
+    };
+    let depth = ctx.location.len().saturating_sub(1);
+    if levels > depth {
+        return Err(Error::InvalidReference {
+            message: format!("Reference {} points above the root", target),
+            span,
+        });
+    }
+    Ok(ctx.location[..depth - levels].to_vec())
 }
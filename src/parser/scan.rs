@@ -0,0 +1,77 @@
+/// The parts of the input that the grammar doesn't produce tokens for, or that need to be
+/// found in input that fails to parse.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum TokenKind {
+    Separator(char),
+    /// A string including its quotes, or up to the end of the input if it isn't closed.
+    String {
+        closed: bool,
+    },
+    LineComment,
+    BlockComment,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Token {
+    pub kind: TokenKind,
+    pub start: usize,
+    pub end: usize,
+}
+
+/// Finds the strings, and the brackets, commas and comments that are not part of one. Like in the
+/// grammar a string ends at the next unescaped quote it starts with, as references inside of
+/// it can't hold that quote, e.g. a fallback literal uses the other one: `"&{a ?? '#000'}"`.
+pub(crate) fn scan(input: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut chars = input.char_indices().peekable();
+    while let Some((start, c)) = chars.next() {
+        let kind = match c {
+            ',' | '{' | '}' | '[' | ']' => TokenKind::Separator(c),
+            '"' | '\'' => {
+                let (mut end, mut closed) = (input.len(), false);
+                while let Some((index, next)) = chars.next() {
+                    match next {
+                        '\\' => {
+                            chars.next();
+                        }
+                        _ if next == c => {
+                            end = index + 1;
+                            closed = true;
+                            break;
+                        }
+                        _ => {}
+                    }
+                }
+                tokens.push(Token {
+                    kind: TokenKind::String { closed },
+                    start,
+                    end,
+                });
+                continue;
+            }
+            '#' => TokenKind::LineComment,
+            '/' if matches!(chars.peek(), Some((_, '/'))) => TokenKind::LineComment,
+            '/' if matches!(chars.peek(), Some((_, '*'))) => TokenKind::BlockComment,
+            _ => continue,
+        };
+        let end = match kind {
+            TokenKind::Separator(_) => start + 1,
+            TokenKind::String { .. } => unreachable!("strings are added right away"),
+            TokenKind::LineComment => {
+                let length = input[start..].find('\n').unwrap_or(input.len() - start);
+                start + length
+            }
+            TokenKind::BlockComment => {
+                let length = input[start + 2..]
+                    .find("*/")
+                    .map_or(input.len() - start, |x| x + 4);
+                start + length
+            }
+        };
+        while matches!(chars.peek(), Some((index, _)) if *index < end) {
+            chars.next();
+        }
+        tokens.push(Token { kind, start, end });
+    }
+    tokens
+}
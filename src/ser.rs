@@ -0,0 +1,711 @@
+use crate::error::Error;
+use crate::options::WriteOptions;
+use crate::tokenizer::{Rule, Tokenizer};
+use crate::Result;
+use pest::Parser;
+use serde::ser::{self, Serialize};
+use std::fmt::Display;
+use std::io;
+
+/// The name of the struct that numbers of `serde_json` are serialized as when its
+/// `arbitrary_precision` feature is on, its only field holds the number as it is written.
+pub(crate) const NUMBER_TOKEN: &str = "$serde_json::private::Number";
+
+/// Writes a value as d4t4 on a single line. With the default options the output is JSON, as
+/// long as no string holds `&{`. That is escaped as `\&{` so that d4t4 doesn't read it as a
+/// reference, which makes it invalid JSON.
+///
+/// ```rust
+///     use d4t4::to_string;
+///     use serde_json::json;
+///
+///     let value = json!({ "border": "1px solid &{color}", "sizes": [1, 2] });
+///     assert_eq!(to_string(&value).unwrap(), r#"{"border":"1px solid \&{color}","sizes":[1,2]}"#);
+/// ```
+pub fn to_string<T: Serialize + ?Sized>(value: &T) -> Result<String> {
+    to_string_with_options(value, &WriteOptions::new())
+}
+
+/// Writes a value as d4t4 with every key and array value on its own line, indented by four
+/// spaces.
+pub fn to_string_pretty<T: Serialize + ?Sized>(value: &T) -> Result<String> {
+    to_string_with_options(value, &WriteOptions::pretty())
+}
+
+/// Writes a value as d4t4 in the style of the options.
+///
+/// ```rust
+///     use d4t4::{to_string_with_options, WriteOptions};
+///     use serde::Serialize;
+///
+///     #[derive(Serialize)]
+///     struct Color {
+///         red: &'static str,
+///     }
+///
+///     let options = WriteOptions::pretty()
+///         .unquoted_keys(true)
+///         .quote('\'')
+///         .trailing_commas(true);
+///     let color = Color { red: "#f00" };
+///     assert_eq!(
+///         to_string_with_options(&color, &options).unwrap(),
+///         "{\n    red: '#f00',\n}"
+///     );
+/// ```
+pub fn to_string_with_options<T: Serialize + ?Sized>(
+    value: &T,
+    options: &WriteOptions,
+) -> Result<String> {
+    let mut output = Vec::new();
+    to_writer_with_options(&mut output, value, options)?;
+    Ok(String::from_utf8(output).expect("the written text should be valid UTF-8"))
+}
+
+/// Writes a value as d4t4 on a single line, see [`to_string`].
+pub fn to_writer<W: io::Write, T: Serialize + ?Sized>(writer: W, value: &T) -> Result<()> {
+    to_writer_with_options(writer, value, &WriteOptions::new())
+}
+
+/// Writes a value as d4t4 on multiple lines, see [`to_string_pretty`].
+pub fn to_writer_pretty<W: io::Write, T: Serialize + ?Sized>(writer: W, value: &T) -> Result<()> {
+    to_writer_with_options(writer, value, &WriteOptions::pretty())
+}
+
+pub fn to_writer_with_options<W: io::Write, T: Serialize + ?Sized>(
+    writer: W,
+    value: &T,
+    options: &WriteOptions,
+) -> Result<()> {
+    value.serialize(&mut Serializer::with_options(writer, options.clone()))
+}
+
+impl ser::Error for Error {
+    fn custom<T: Display>(message: T) -> Self {
+        Error::Serialize {
+            message: message.to_string(),
+        }
+    }
+}
+
+fn get_io_error(error: io::Error) -> Error {
+    Error::Serialize {
+        message: format!("Failed to write: {}", error),
+    }
+}
+
+/// Writes values as d4t4 text. Structs and maps are written as objects, sequences and tuples
+/// as arrays and enum variants with data as an object with the variant as its only key.
+pub struct Serializer<W> {
+    writer: W,
+    options: WriteOptions,
+    depth: usize,
+}
+
+impl<W: io::Write> Serializer<W> {
+    pub fn new(writer: W) -> Self {
+        Self::with_options(writer, WriteOptions::new())
+    }
+
+    pub fn with_options(writer: W, options: WriteOptions) -> Self {
+        Self {
+            writer,
+            options,
+            depth: 0,
+        }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    fn write(&mut self, text: &str) -> Result<()> {
+        self.writer.write_all(text.as_bytes()).map_err(get_io_error)
+    }
+
+    fn write_string(&mut self, string: &str) -> Result<()> {
+        let string = write_string(string, self.options.quote);
+        self.write(&string)
+    }
+
+    fn write_key(&mut self, key: &str) -> Result<()> {
+        if self.options.unquoted_keys && is_identifier(key) {
+            self.write(key)?;
+        } else {
+            self.write_string(key)?;
+        }
+        self.write(if self.is_pretty() { ": " } else { ":" })
+    }
+
+    fn write_new_line(&mut self) -> Result<()> {
+        if self.is_pretty() {
+            let indent = self.options.indent.repeat(self.depth);
+            self.write("\n")?;
+            self.write(&indent)?;
+        }
+        Ok(())
+    }
+
+    fn is_pretty(&self) -> bool {
+        !self.options.indent.is_empty()
+    }
+
+    /// Opens an object or array, `variant` being the enum variant it is the data of.
+    fn begin(&mut self, open: &str, variant: Option<&str>) -> Result<Compound<'_, W>> {
+        if let Some(variant) = variant {
+            self.begin_variant(variant)?;
+        }
+        self.write(open)?;
+        self.depth += 1;
+        Ok(Compound {
+            serializer: self,
+            is_empty: true,
+            is_variant: variant.is_some(),
+            is_number: false,
+        })
+    }
+
+    /// Opens the object that holds the data of an enum variant.
+    fn begin_variant(&mut self, variant: &str) -> Result<()> {
+        self.write("{")?;
+        self.depth += 1;
+        self.write_new_line()?;
+        self.write_key(variant)
+    }
+
+    /// Closes an object or array after its values.
+    fn end(&mut self, close: &str, is_empty: bool) -> Result<()> {
+        self.depth -= 1;
+        if !is_empty {
+            if self.is_pretty() && self.options.trailing_commas {
+                self.write(",")?;
+            }
+            self.write_new_line()?;
+        }
+        self.write(close)
+    }
+
+    fn write_number<T: Display>(&mut self, number: T) -> Result<()> {
+        self.write(&number.to_string())
+    }
+
+    /// Writes the shortest text that reads back as the same `f32` or `f64`.
+    fn write_float<F: ryu::Float>(&mut self, float: F, is_finite: bool) -> Result<()> {
+        // Written like JSON, e.g. `1.0` rather than `1`, and `null` for NaN and infinity
+        if is_finite {
+            self.write(ryu::Buffer::new().format_finite(float))
+        } else {
+            self.write("null")
+        }
+    }
+}
+
+/// An object or array that is being written.
+pub struct Compound<'a, W> {
+    serializer: &'a mut Serializer<W>,
+    is_empty: bool,
+    /// Whether the object or array is wrapped in an object of its enum variant.
+    is_variant: bool,
+    /// Whether this is a number of `serde_json` that is written as its text.
+    is_number: bool,
+}
+
+impl<W: io::Write> Compound<'_, W> {
+    fn separate(&mut self) -> Result<()> {
+        if !self.is_empty {
+            self.serializer.write(",")?;
+        }
+        self.is_empty = false;
+        self.serializer.write_new_line()
+    }
+
+    fn finish(self, close: &str) -> Result<()> {
+        self.serializer.end(close, self.is_empty)?;
+        if self.is_variant {
+            self.serializer.end("}", false)?;
+        }
+        Ok(())
+    }
+}
+
+impl<'a, W: io::Write> ser::Serializer for &'a mut Serializer<W> {
+    type Ok = ();
+    type Error = Error;
+    type SerializeSeq = Compound<'a, W>;
+    type SerializeTuple = Compound<'a, W>;
+    type SerializeTupleStruct = Compound<'a, W>;
+    type SerializeTupleVariant = Compound<'a, W>;
+    type SerializeMap = Compound<'a, W>;
+    type SerializeStruct = Compound<'a, W>;
+    type SerializeStructVariant = Compound<'a, W>;
+
+    fn serialize_bool(self, value: bool) -> Result<()> {
+        self.write(if value { "true" } else { "false" })
+    }
+
+    fn serialize_i8(self, value: i8) -> Result<()> {
+        self.write_number(value)
+    }
+
+    fn serialize_i16(self, value: i16) -> Result<()> {
+        self.write_number(value)
+    }
+
+    fn serialize_i32(self, value: i32) -> Result<()> {
+        self.write_number(value)
+    }
+
+    fn serialize_i64(self, value: i64) -> Result<()> {
+        self.write_number(value)
+    }
+
+    fn serialize_i128(self, value: i128) -> Result<()> {
+        self.write_number(value)
+    }
+
+    fn serialize_u8(self, value: u8) -> Result<()> {
+        self.write_number(value)
+    }
+
+    fn serialize_u16(self, value: u16) -> Result<()> {
+        self.write_number(value)
+    }
+
+    fn serialize_u32(self, value: u32) -> Result<()> {
+        self.write_number(value)
+    }
+
+    fn serialize_u64(self, value: u64) -> Result<()> {
+        self.write_number(value)
+    }
+
+    fn serialize_u128(self, value: u128) -> Result<()> {
+        self.write_number(value)
+    }
+
+    fn serialize_f32(self, value: f32) -> Result<()> {
+        self.write_float(value, value.is_finite())
+    }
+
+    fn serialize_f64(self, value: f64) -> Result<()> {
+        self.write_float(value, value.is_finite())
+    }
+
+    fn serialize_char(self, value: char) -> Result<()> {
+        self.write_string(value.encode_utf8(&mut [0; 4]))
+    }
+
+    fn serialize_str(self, value: &str) -> Result<()> {
+        self.write_string(value)
+    }
+
+    fn serialize_bytes(self, value: &[u8]) -> Result<()> {
+        use ser::SerializeSeq;
+        let mut seq = self.serialize_seq(Some(value.len()))?;
+        for byte in value {
+            seq.serialize_element(byte)?;
+        }
+        seq.end()
+    }
+
+    fn serialize_none(self) -> Result<()> {
+        self.serialize_unit()
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<()> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<()> {
+        self.write("null")
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<()> {
+        self.serialize_unit()
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+    ) -> Result<()> {
+        self.write_string(variant)
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        self.begin_variant(variant)?;
+        value.serialize(&mut *self)?;
+        self.end("}", false)
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Compound<'a, W>> {
+        self.begin("[", None)
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<Compound<'a, W>> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, len: usize) -> Result<Compound<'a, W>> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<Compound<'a, W>> {
+        self.begin("[", Some(variant))
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Compound<'a, W>> {
+        self.begin("{", None)
+    }
+
+    fn serialize_struct(self, name: &'static str, len: usize) -> Result<Compound<'a, W>> {
+        if name == NUMBER_TOKEN {
+            return Ok(Compound {
+                serializer: self,
+                is_empty: true,
+                is_variant: false,
+                is_number: true,
+            });
+        }
+        self.serialize_map(Some(len))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<Compound<'a, W>> {
+        self.begin("{", Some(variant))
+    }
+}
+
+impl<W: io::Write> ser::SerializeSeq for Compound<'_, W> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        self.separate()?;
+        value.serialize(&mut *self.serializer)
+    }
+
+    fn end(self) -> Result<()> {
+        Compound::finish(self, "]")
+    }
+}
+
+impl<W: io::Write> ser::SerializeTuple for Compound<'_, W> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<()> {
+        Compound::finish(self, "]")
+    }
+}
+
+impl<W: io::Write> ser::SerializeTupleStruct for Compound<'_, W> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<()> {
+        Compound::finish(self, "]")
+    }
+}
+
+impl<W: io::Write> ser::SerializeTupleVariant for Compound<'_, W> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<()> {
+        Compound::finish(self, "]")
+    }
+}
+
+impl<W: io::Write> ser::SerializeMap for Compound<'_, W> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<()> {
+        self.separate()?;
+        let key = key.serialize(KeySerializer)?;
+        self.serializer.write_key(&key)
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        value.serialize(&mut *self.serializer)
+    }
+
+    fn end(self) -> Result<()> {
+        Compound::finish(self, "}")
+    }
+}
+
+impl<W: io::Write> ser::SerializeStruct for Compound<'_, W> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        if self.is_number {
+            let number = value.serialize(KeySerializer)?;
+            return self.serializer.write(&number);
+        }
+        self.separate()?;
+        self.serializer.write_key(key)?;
+        value.serialize(&mut *self.serializer)
+    }
+
+    fn end(self) -> Result<()> {
+        if self.is_number {
+            return Ok(());
+        }
+        Compound::finish(self, "}")
+    }
+}
+
+impl<W: io::Write> ser::SerializeStructVariant for Compound<'_, W> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        ser::SerializeStruct::serialize_field(self, key, value)
+    }
+
+    fn end(self) -> Result<()> {
+        Compound::finish(self, "}")
+    }
+}
+
+/// Turns map keys into strings, keys can be strings, numbers, booleans and chars like in
+/// JSON.
+struct KeySerializer;
+
+fn get_key_error() -> Error {
+    ser::Error::custom("key must be a string, number, bool or char")
+}
+
+macro_rules! serialize_key_display {
+    ($($method:ident: $type:ty),*) => {
+        $(
+            fn $method(self, value: $type) -> Result<String> {
+                Ok(value.to_string())
+            }
+        )*
+    };
+}
+
+impl ser::Serializer for KeySerializer {
+    type Ok = String;
+    type Error = Error;
+    type SerializeSeq = ser::Impossible<String, Error>;
+    type SerializeTuple = ser::Impossible<String, Error>;
+    type SerializeTupleStruct = ser::Impossible<String, Error>;
+    type SerializeTupleVariant = ser::Impossible<String, Error>;
+    type SerializeMap = ser::Impossible<String, Error>;
+    type SerializeStruct = ser::Impossible<String, Error>;
+    type SerializeStructVariant = ser::Impossible<String, Error>;
+
+    serialize_key_display! {
+        serialize_bool: bool,
+        serialize_i8: i8,
+        serialize_i16: i16,
+        serialize_i32: i32,
+        serialize_i64: i64,
+        serialize_i128: i128,
+        serialize_u8: u8,
+        serialize_u16: u16,
+        serialize_u32: u32,
+        serialize_u64: u64,
+        serialize_u128: u128,
+        serialize_char: char,
+        serialize_str: &str
+    }
+
+    fn serialize_f32(self, _value: f32) -> Result<String> {
+        Err(get_key_error())
+    }
+
+    fn serialize_f64(self, _value: f64) -> Result<String> {
+        Err(get_key_error())
+    }
+
+    fn serialize_bytes(self, _value: &[u8]) -> Result<String> {
+        Err(get_key_error())
+    }
+
+    fn serialize_none(self) -> Result<String> {
+        Err(get_key_error())
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, _value: &T) -> Result<String> {
+        Err(get_key_error())
+    }
+
+    fn serialize_unit(self) -> Result<String> {
+        Err(get_key_error())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<String> {
+        Err(get_key_error())
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+    ) -> Result<String> {
+        Ok(variant.to_string())
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<String> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<String> {
+        Err(get_key_error())
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq> {
+        Err(get_key_error())
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple> {
+        Err(get_key_error())
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleStruct> {
+        Err(get_key_error())
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant> {
+        Err(get_key_error())
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap> {
+        Err(get_key_error())
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeStruct> {
+        Err(get_key_error())
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant> {
+        Err(get_key_error())
+    }
+
+    fn collect_str<T: Display + ?Sized>(self, value: &T) -> Result<String> {
+        Ok(value.to_string())
+    }
+}
+
+/// Whether the key can be written without quotes, as of the `identifier` rule.
+pub(crate) fn is_identifier(key: &str) -> bool {
+    let mut chars = key.chars();
+    let is_valid = match chars.next() {
+        Some(c) if c.is_alphabetic() || c.is_ascii_digit() => {
+            chars.all(|c| c.is_alphabetic() || c.is_ascii_digit() || c == '_' || c == '-')
+        }
+        _ => false,
+    };
+    // Letters are checked by the grammar too, as it doesn't know every alphabetic character
+    is_valid
+        && matches!(
+            Tokenizer::parse(Rule::identifier, key).map(|mut pairs| pairs.next()),
+            Ok(Some(pair)) if pair.as_str().len() == key.len()
+        )
+}
+
+/// Writes a string in quotes, escaping what would otherwise end it or be read as a
+/// reference.
+pub(crate) fn write_string(string: &str, quote: char) -> String {
+    let mut written = String::with_capacity(string.len() + 2);
+    written.push(quote);
+    let mut chars = string.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => written.push_str("\\\\"),
+            '\n' => written.push_str("\\n"),
+            '\r' => written.push_str("\\r"),
+            '\t' => written.push_str("\\t"),
+            '\u{8}' => written.push_str("\\b"),
+            '\u{c}' => written.push_str("\\f"),
+            '&' if chars.peek() == Some(&'{') => written.push_str("\\&"),
+            c if c == quote => {
+                written.push('\\');
+                written.push(c);
+            }
+            c if c < ' ' => written.push_str(&format!("\\u{:04x}", c as u32)),
+            c => written.push(c),
+        }
+    }
+    written.push(quote);
+    written
+}
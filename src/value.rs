@@ -0,0 +1,448 @@
+//! A value that keeps the spans and comments of the document it is parsed from, see
+//! [`parse_spanned`].
+
+use crate::document::{is_value, Document, Element, Node, NodeKind, Token, TokenKind};
+use crate::error::{Error, Span};
+use crate::options::{DuplicateKeys, ParseOptions};
+use crate::parser::parse_key;
+use crate::Result;
+use serde_json::{Map, Number};
+use std::fmt;
+
+/// A parsed value that remembers where it is written, see [`parse_spanned`]. Unlike
+/// [`serde_json::Value`] objects keep the order of their keys.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Number(Number),
+    String(String, Quote),
+    Array(Vec<Spanned<Value>>),
+    Object(Vec<Member>),
+}
+
+/// The quotes of a string or key as it is written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Quote {
+    Double,
+    Single,
+    /// An unquoted key.
+    None,
+}
+
+/// A key and its value. The comments around them are found on the value.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Member {
+    pub key: Spanned<Key>,
+    pub value: Spanned<Value>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Key {
+    pub name: String,
+    pub quote: Quote,
+}
+
+/// A value along with its span in the input and the comments around it.
+///
+/// Values that come from a reference, e.g. the object that `"&{colors}"` resolves to, are
+/// spanned by the reference.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Spanned<T> {
+    pub value: T,
+    pub span: Span,
+    pub comments: Comments,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Comments {
+    /// The comments on the lines in front of the value.
+    pub leading: Vec<Comment>,
+    /// A comment on the same line after the value, and for the last value of an object or
+    /// array the comments in front of its closing bracket.
+    pub trailing: Vec<Comment>,
+}
+
+/// A comment as it is written, e.g. `// note` or `/* note */`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Comment {
+    pub text: String,
+    pub span: Span,
+}
+
+/// Parses the input into a [`Value`] that keeps the span and comments of every value, with
+/// its references resolved.
+///
+/// ```rust
+///     use d4t4::parse_spanned;
+///     use d4t4::value::{Quote, Value};
+///
+///     let value = parse_spanned("{\n    // the best color\n    red: '#f00',\n}").unwrap();
+///     let red = value.value.get("red").unwrap();
+///     assert_eq!(red.value, Value::String("#f00".to_string(), Quote::Single));
+///     assert_eq!((red.span.line, red.span.column), (3, 10));
+///     assert_eq!(red.comments.leading[0].text, "// the best color");
+///     assert_eq!(serde_json::Value::from(value)["red"], "#f00");
+/// ```
+pub fn parse_spanned(input: &str) -> Result<Spanned<Value>> {
+    parse_spanned_with_options(input, &ParseOptions::default())
+}
+
+pub fn parse_spanned_with_options(input: &str, options: &ParseOptions) -> Result<Spanned<Value>> {
+    let resolved = crate::parse_with_options(input, options)?;
+    let document = Document::parse(input)?;
+    let builder = Builder { options };
+    let (mut items, _) = builder.build_items(document.root(), &mut |element, _| {
+        builder.build_value(element, &resolved)
+    })?;
+    Ok(items.remove(0))
+}
+
+impl Value {
+    /// The value of a key of an object.
+    pub fn get(&self, key: &str) -> Option<&Spanned<Value>> {
+        match self {
+            Value::Object(members) => members
+                .iter()
+                .find(|member| member.key.value.name == key)
+                .map(|member| &member.value),
+            _ => None,
+        }
+    }
+
+    /// The number, its `Display` writes it the way it is written in the document when the
+    /// `arbitrary_precision` feature is on.
+    pub fn as_number(&self) -> Option<&Number> {
+        match self {
+            Value::Number(number) => Some(number),
+            _ => None,
+        }
+    }
+
+    /// The number as an `i128`, if it is an integer that fits. Integers that don't fit an
+    /// `i64` or `u64` need the `arbitrary_precision` feature.
+    pub fn as_i128(&self) -> Option<i128> {
+        self.as_number()?.to_string().parse().ok()
+    }
+
+    /// The number as a `u128`, see [`as_i128`](Value::as_i128).
+    pub fn as_u128(&self) -> Option<u128> {
+        self.as_number()?.to_string().parse().ok()
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(string, _) => Some(string),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[Spanned<Value>]> {
+        match self {
+            Value::Array(array) => Some(array),
+            _ => None,
+        }
+    }
+
+    pub fn as_object(&self) -> Option<&[Member]> {
+        match self {
+            Value::Object(members) => Some(members),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    /// Writes the value as JSON.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        serde_json::Value::from(self.clone()).fmt(f)
+    }
+}
+
+impl<T> Spanned<T> {
+    fn new(value: T, span: Span) -> Self {
+        Self {
+            value,
+            span,
+            comments: Comments::default(),
+        }
+    }
+}
+
+impl From<Value> for serde_json::Value {
+    fn from(value: Value) -> Self {
+        match value {
+            Value::Null => serde_json::Value::Null,
+            Value::Bool(bool) => serde_json::Value::Bool(bool),
+            Value::Number(number) => serde_json::Value::Number(number),
+            Value::String(string, _) => serde_json::Value::String(string),
+            Value::Array(array) => array.into_iter().map(Self::from).collect(),
+            Value::Object(members) => serde_json::Value::Object(
+                members
+                    .into_iter()
+                    .map(|member| (member.key.value.name, member.value.into()))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+impl From<Spanned<Value>> for serde_json::Value {
+    fn from(value: Spanned<Value>) -> Self {
+        value.value.into()
+    }
+}
+
+/// Converts values without spans, strings and keys are in double quotes.
+impl From<serde_json::Value> for Value {
+    fn from(value: serde_json::Value) -> Self {
+        from_json(value, Span::default())
+    }
+}
+
+impl From<serde_json::Value> for Spanned<Value> {
+    fn from(value: serde_json::Value) -> Self {
+        Spanned::new(value.into(), Span::default())
+    }
+}
+
+/// Converts a value with the same span for all of its values.
+fn from_json(value: serde_json::Value, span: Span) -> Value {
+    match value {
+        serde_json::Value::Null => Value::Null,
+        serde_json::Value::Bool(bool) => Value::Bool(bool),
+        serde_json::Value::Number(number) => Value::Number(number),
+        serde_json::Value::String(string) => Value::String(string, Quote::Double),
+        serde_json::Value::Array(array) => Value::Array(
+            array
+                .into_iter()
+                .map(|value| Spanned::new(from_json(value, span), span))
+                .collect(),
+        ),
+        serde_json::Value::Object(object) => Value::Object(
+            object
+                .into_iter()
+                .map(|(name, value)| Member {
+                    key: Spanned::new(
+                        Key {
+                            name,
+                            quote: Quote::Double,
+                        },
+                        span,
+                    ),
+                    value: Spanned::new(from_json(value, span), span),
+                })
+                .collect(),
+        ),
+    }
+}
+
+struct Builder<'a> {
+    options: &'a ParseOptions,
+}
+
+impl Builder<'_> {
+    /// Builds a value of the document, `resolved` being the same value with its references
+    /// resolved.
+    fn build_value(
+        &self,
+        element: &Element,
+        resolved: &serde_json::Value,
+    ) -> Result<Spanned<Value>> {
+        let (node, span) = match element {
+            Element::Token(token) => {
+                return Ok(Spanned::new(get_token_value(token, resolved), token.span()))
+            }
+            Element::Node(node) => (node, node.span()),
+        };
+        let (value, comments) = match (node.kind(), resolved) {
+            (NodeKind::Array, serde_json::Value::Array(array)) => {
+                let (values, comments) =
+                    self.build_items(node, &mut |element, index| match array.get(index) {
+                        Some(resolved) => self.build_value(element, resolved),
+                        None => Err(get_mismatch_error(element)),
+                    })?;
+                (Value::Array(values), comments)
+            }
+            (NodeKind::Object, serde_json::Value::Object(object)) => {
+                let (members, comments) = self.build_members(node, object)?;
+                (Value::Object(members), comments)
+            }
+            _ => return Err(get_mismatch_error(element)),
+        };
+        let mut value = Spanned::new(value, span);
+        // The comments of an empty object or array
+        value.comments.trailing = comments;
+        Ok(value)
+    }
+
+    /// Builds the members of an object. Of a key that is given more than once only the
+    /// value that is kept by the parser is built, the comments of the others are kept on it.
+    fn build_members(
+        &self,
+        node: &Node,
+        object: &Map<String, serde_json::Value>,
+    ) -> Result<(Vec<Member>, Vec<Comment>)> {
+        let mut keys = Vec::new();
+        for member in node.members() {
+            let token = member.key().expect("member should have a key");
+            let name = parse_key(token.text(), self.options)?;
+            let quote = match token.kind() {
+                TokenKind::Identifier => Quote::None,
+                _ => get_quote(token),
+            };
+            keys.push(Spanned::new(Key { name, quote }, token.span()));
+        }
+        let is_kept = |index: usize| {
+            let name = &keys[index].value.name;
+            let mut same = keys
+                .iter()
+                .enumerate()
+                .filter(|(_, key)| &key.value.name == name);
+            let kept = match self.options.get_dialect().duplicate_keys {
+                DuplicateKeys::Last => same.next_back(),
+                DuplicateKeys::First | DuplicateKeys::Error => same.next(),
+            };
+            kept.map(|(kept, _)| kept) == Some(index)
+        };
+        let (values, comments) = self.build_items(node, &mut |element, index| {
+            let member = match element {
+                Element::Node(member) => member,
+                Element::Token(_) => unreachable!("objects only hold members"),
+            };
+            let value = member.value().expect("member should have a value");
+            if !is_kept(index) {
+                // Dropped, so only its comments are of use
+                return Ok(Spanned::new(Value::Null, member.span()));
+            }
+            match object.get(&keys[index].value.name) {
+                Some(resolved) => self.build_value(value, resolved),
+                None => Err(get_mismatch_error(element)),
+            }
+        })?;
+        let mut members: Vec<Member> = Vec::new();
+        for (index, (key, mut value)) in keys.iter().cloned().zip(values).enumerate() {
+            let previous = members
+                .iter()
+                .position(|member| member.key.value.name == key.value.name);
+            match previous {
+                Some(previous) if is_kept(index) => {
+                    // Kept in the place of the first one, like the parsed object does
+                    let comments = std::mem::take(&mut members[previous].value.comments);
+                    value.comments.leading.splice(0..0, comments.leading);
+                    value.comments.trailing.splice(0..0, comments.trailing);
+                    members[previous] = Member { key, value };
+                }
+                Some(previous) => {
+                    let comments = value.comments;
+                    let kept = &mut members[previous].value.comments;
+                    kept.leading.extend(comments.leading);
+                    kept.trailing.extend(comments.trailing);
+                }
+                None => members.push(Member { key, value }),
+            }
+        }
+        Ok((members, comments))
+    }
+
+    /// Builds the values of an object, an array or the document and adds the comments
+    /// around them. Comments that belong to no value are returned as well.
+    fn build_items(
+        &self,
+        node: &Node,
+        build: &mut dyn FnMut(&Element, usize) -> Result<Spanned<Value>>,
+    ) -> Result<(Vec<Spanned<Value>>, Vec<Comment>)> {
+        let mut items: Vec<Spanned<Value>> = Vec::new();
+        let mut leading = Vec::new();
+        // Whether a comment would be on the same line as the last value
+        let mut is_same_line = false;
+        for child in node.children() {
+            let token = match child {
+                Element::Token(token) if !is_value(token.kind()) => token,
+                child => {
+                    let mut item = build(child, items.len())?;
+                    item.comments.leading = std::mem::take(&mut leading);
+                    if let Element::Node(member) = child {
+                        if member.kind() == NodeKind::Member {
+                            item.comments.leading.extend(get_comments(member));
+                        }
+                    }
+                    items.push(item);
+                    is_same_line = true;
+                    continue;
+                }
+            };
+            match token.kind() {
+                TokenKind::Whitespace if token.text().contains('\n') => is_same_line = false,
+                TokenKind::LineComment | TokenKind::BlockComment => {
+                    let comment = Comment {
+                        text: token.text().trim_end().to_string(),
+                        span: token.span(),
+                    };
+                    match items.last_mut() {
+                        Some(item) if is_same_line => item.comments.trailing.push(comment),
+                        _ => leading.push(comment),
+                    }
+                }
+                _ => {}
+            }
+        }
+        // Comments in front of the closing bracket
+        if let Some(item) = items.last_mut() {
+            item.comments.trailing.append(&mut leading);
+        }
+        Ok((items, leading))
+    }
+}
+
+/// The document and the parsed value always match, this is only reported if they don't.
+fn get_mismatch_error(element: &Element) -> Error {
+    let span = match element {
+        Element::Token(token) => token.span(),
+        Element::Node(node) => node.span(),
+    };
+    Error::Syntax {
+        message: "Failed to match the value to the document".to_string(),
+        span,
+    }
+}
+
+/// The comments between the key and the value of a member.
+fn get_comments(member: &Node) -> Vec<Comment> {
+    member
+        .children()
+        .iter()
+        .filter_map(|child| match child {
+            Element::Token(token)
+                if matches!(
+                    token.kind(),
+                    TokenKind::LineComment | TokenKind::BlockComment
+                ) =>
+            {
+                Some(Comment {
+                    text: token.text().trim_end().to_string(),
+                    span: token.span(),
+                })
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+fn get_quote(token: &Token) -> Quote {
+    match token.text().chars().next() {
+        Some('\'') => Quote::Single,
+        _ => Quote::Double,
+    }
+}
+
+/// The value of a number, string etc. A string that references another value takes its
+/// type.
+fn get_token_value(token: &Token, resolved: &serde_json::Value) -> Value {
+    match resolved {
+        serde_json::Value::String(string) if token.kind() == TokenKind::String => {
+            Value::String(string.clone(), get_quote(token))
+        }
+        resolved => from_json(resolved.clone(), token.span()),
+    }
+}
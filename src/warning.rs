@@ -0,0 +1,28 @@
+use crate::error::Span;
+use thiserror::Error as ThisError;
+
+/// Something that doesn't stop a document from being parsed but likely is a mistake, see
+/// [`parse_with_warnings`](crate::parse_with_warnings).
+#[derive(ThisError, Debug, Clone, PartialEq, Eq)]
+pub enum Warning {
+    /// An object is treated as a set of definitions as soon as one of its values is
+    /// referenced, the values of it that never are get reported.
+    #[error("{path} is never referenced while other values of its object are, at {span}")]
+    UnusedDefinition { path: String, span: Span },
+    /// A key holding a `.` can't be referenced when there is a nested value with the same
+    /// path, e.g. `"a.b"` next to `a: { b: 1 }`.
+    #[error("Key {key} is shadowed by the path {key} at {span}")]
+    ShadowedKey { key: String, span: Span },
+    #[error("Reference {target} resolved to an empty string at {span}")]
+    EmptyReference { target: String, span: Span },
+}
+
+impl Warning {
+    pub fn span(&self) -> Span {
+        match self {
+            Warning::UnusedDefinition { span, .. }
+            | Warning::ShadowedKey { span, .. }
+            | Warning::EmptyReference { span, .. } => *span,
+        }
+    }
+}
@@ -0,0 +1,9 @@
+/// Snapshots a value as JSON with its keys sorted, so that the snapshots are the same with
+/// and without the `preserve_order` feature. Tests of the order itself assert it directly.
+macro_rules! assert_json_snapshot {
+    ($value:expr) => {
+        insta::with_settings!({ sort_maps => true }, {
+            insta::assert_json_snapshot!($value);
+        })
+    };
+}
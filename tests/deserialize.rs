@@ -0,0 +1,137 @@
+use anyhow::Result;
+use d4t4::{from_reader, from_str, Error};
+use indoc::indoc;
+use serde::Deserialize;
+use std::collections::HashMap;
+
+#[derive(Deserialize, Debug, PartialEq)]
+struct Config {
+    name: String,
+    servers: Vec<Server>,
+    colors: HashMap<String, String>,
+    timeout: Option<f64>,
+    mode: Mode,
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+struct Server {
+    host: String,
+    port: u16,
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+enum Mode {
+    Debug,
+    Release { optimize: u8 },
+}
+
+#[test]
+fn it_deserializes_structs() -> Result<()> {
+    let config: Config = from_str(indoc!(
+        r##"
+        {
+            name: 'app',
+            defaults: { host: 'localhost', port: 8080 },
+            servers: [
+                "&{defaults}",
+                { host: "&{defaults.host}", port: 8081 },
+            ],
+            colors: { red: '#f00' }, // comments are fine
+            timeout: null,
+            mode: { release: { optimize: 3 } },
+        }
+        "##
+    ))?;
+    assert_eq!(config.name, "app");
+    assert_eq!(
+        config.servers,
+        vec![
+            Server {
+                host: "localhost".to_string(),
+                port: 8080
+            },
+            Server {
+                host: "localhost".to_string(),
+                port: 8081
+            },
+        ]
+    );
+    assert_eq!(config.colors["red"], "#f00");
+    assert_eq!(config.timeout, None);
+    assert_eq!(config.mode, Mode::Release { optimize: 3 });
+    assert_eq!(from_str::<Mode>("'debug'")?, Mode::Debug);
+    Ok(())
+}
+
+#[test]
+fn it_points_out_where_deserializing_failed() -> Result<()> {
+    let input = indoc!(
+        r#"
+        {
+            servers: [
+                { host: 'localhost', port: 8080 },
+                { host: 'localhost', port: 'http' },
+            ],
+        }
+        "#
+    );
+    let error = from_str::<HashMap<String, Vec<Server>>>(input).unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        r#"invalid type: string "http", expected u16 at 4:36"#
+    );
+
+    let error = from_str::<Server>("{\n    host: 'localhost',\n}").unwrap_err();
+    assert!(matches!(error, Error::Deserialize { .. }));
+    assert_eq!(error.to_string(), "missing field `port` at 1:1");
+
+    let error = from_str::<Server>("{ host: 'localhost', port: 70000 }").unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "invalid value: integer `70000`, expected u16 at 1:28"
+    );
+    Ok(())
+}
+
+#[test]
+fn it_points_out_unknown_fields_at_their_key() -> Result<()> {
+    #[derive(Deserialize, Debug)]
+    #[serde(deny_unknown_fields)]
+    #[allow(dead_code)]
+    struct Strict {
+        port: u16,
+    }
+    let error = from_str::<Strict>("{\n    port: 1,\n    prot: 2,\n}").unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "unknown field `prot`, expected `port` at 3:5"
+    );
+    let diagnostic = error.diagnostic();
+    assert_eq!(diagnostic.labels[0].span.line, 3);
+    Ok(())
+}
+
+#[test]
+fn it_points_referenced_values_to_the_reference() -> Result<()> {
+    #[derive(Deserialize, Debug)]
+    #[allow(dead_code)]
+    struct Servers {
+        server: Server,
+    }
+    let input = "{ defaults: { host: 1 }, server: '&{defaults}' }";
+    let error = from_str::<Servers>(input).unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "invalid type: integer `1`, expected a string at 1:34"
+    );
+    Ok(())
+}
+
+#[test]
+fn it_deserializes_from_readers() -> Result<()> {
+    let server: Server = from_reader("{ host: 'localhost', port: 8080 }".as_bytes())?;
+    assert_eq!(server.port, 8080);
+    assert!(from_reader::<_, Server>("{ host: ".as_bytes()).is_err());
+    Ok(())
+}
@@ -0,0 +1,178 @@
+use anyhow::Result;
+use d4t4::{parse_with_options, DuplicateKeys, Error, Extension, ParseOptions, Root};
+use indoc::indoc;
+
+fn get_error(markup: &str, options: ParseOptions) -> String {
+    parse_with_options(markup, &options)
+        .expect_err("markup should fail to parse")
+        .to_string()
+}
+
+#[test]
+fn it_can_turn_off_trailing_commas() {
+    let options = ParseOptions::new().trailing_commas(false);
+    assert_eq!(
+        get_error("[1, 2, 3,]", options),
+        "Unsupported trailing comma at 1:9"
+    );
+}
+
+#[test]
+fn it_can_turn_off_unquoted_keys() {
+    let options = ParseOptions::new().unquoted_keys(false);
+    assert_eq!(
+        get_error(r#"{ "a": 1, b: 2 }"#, options),
+        "Unsupported unquoted key at 1:11"
+    );
+}
+
+#[test]
+fn it_can_turn_off_single_quotes() {
+    let options = ParseOptions::new().single_quotes(false);
+    assert_eq!(
+        get_error(r#"{ "a": 'b' }"#, options),
+        "Unsupported single-quoted string at 1:8"
+    );
+    let options = ParseOptions::new().single_quotes(false);
+    assert_eq!(
+        get_error(r#"{ "a": "\'" }"#, options),
+        "Unsupported escaped single quote at 1:9"
+    );
+}
+
+#[test]
+fn it_can_turn_off_comments() {
+    let markup = indoc!(
+        r#"{
+            // line
+            "a": 1, # hash
+            /* block */
+        }"#
+    );
+    let options = ParseOptions::new().line_comments(false);
+    assert_eq!(
+        get_error(markup, options),
+        "Unsupported line comment at 2:5"
+    );
+    let options = ParseOptions::new().block_comments(false);
+    assert_eq!(
+        get_error(markup, options),
+        "Unsupported block comment at 4:5"
+    );
+}
+
+#[test]
+fn it_ignores_comment_characters_inside_of_references() -> Result<()> {
+    let options = ParseOptions::new().line_comments(false);
+    let x = parse_with_options(r#"{ c: "&{a ?? '#000'}" }"#, &options)?;
+    assert_eq!(x["c"], "#000");
+    let x = parse_with_options(r#"{ c: '&{a ?? "//"}' }"#, &options)?;
+    assert_eq!(x["c"], "//");
+    let error = get_error(r##"{ c: "&{a ?? "#000"}" }"##, options);
+    assert!(!error.contains("comment"), "{}", error);
+    Ok(())
+}
+
+#[test]
+fn it_can_turn_off_references() -> Result<()> {
+    let markup = indoc!(
+        r#"{
+            "a": 1,
+            "b": "&{a}",
+            "c": "&{",
+        }"#
+    );
+    let options = ParseOptions::new().references(false);
+    let json = parse_with_options(markup, &options)?;
+    assert_eq!(json["b"], "&{a}");
+    assert_eq!(json["c"], "&{");
+    let options = ParseOptions::new().references(false);
+    assert_eq!(
+        get_error(r#"{ "a": "\&{" }"#, options),
+        "Unsupported reference at 1:9"
+    );
+    Ok(())
+}
+
+#[test]
+fn it_can_turn_off_control_characters() {
+    let options = ParseOptions::new().control_characters(false);
+    assert_eq!(
+        get_error("{ \"a\": \"b\n\tc\" }", options),
+        "Unsupported unescaped control character at 1:10"
+    );
+}
+
+#[test]
+fn it_only_allows_comments() -> Result<()> {
+    let options = || {
+        ParseOptions::new()
+            .trailing_commas(false)
+            .unquoted_keys(false)
+            .single_quotes(false)
+            .references(false)
+            .control_characters(false)
+    };
+    let markup = indoc!(
+        r#"{
+            // a comment
+            "a": "&{b}" /* another one */
+        }"#
+    );
+    assert_eq!(parse_with_options(markup, &options())?["a"], "&{b}");
+    let x = parse_with_options("{ a: 1 }", &options()).err().unwrap();
+    assert!(matches!(
+        x,
+        Error::Extension {
+            extension: Extension::UnquotedKey,
+            ..
+        }
+    ));
+    Ok(())
+}
+
+#[test]
+fn it_can_keep_the_first_or_last_duplicate_key() -> Result<()> {
+    let markup = indoc!(
+        r#"{
+            color: "red",
+            border: "&{color}",
+            color: "green",
+        }"#
+    );
+    let options = ParseOptions::new().duplicate_keys(DuplicateKeys::First);
+    let json = parse_with_options(markup, &options)?;
+    assert_eq!(
+        (&json["color"], &json["border"]),
+        (&"red".into(), &"red".into())
+    );
+    let options = ParseOptions::new().duplicate_keys(DuplicateKeys::Last);
+    let json = parse_with_options(markup, &options)?;
+    assert_eq!(
+        (&json["color"], &json["border"]),
+        (&"green".into(), &"green".into())
+    );
+    Ok(())
+}
+
+#[test]
+fn it_drops_references_of_skipped_duplicate_keys() -> Result<()> {
+    let markup = indoc!(
+        r#"{
+            a: "x",
+            b: "1 &{a}",
+            b: "2 &{a} &{a}",
+        }"#
+    );
+    let options = ParseOptions::new().duplicate_keys(DuplicateKeys::First);
+    assert_eq!(parse_with_options(markup, &options)?["b"], "1 x");
+    let options = ParseOptions::new().duplicate_keys(DuplicateKeys::Last);
+    assert_eq!(parse_with_options(markup, &options)?["b"], "2 x x");
+    Ok(())
+}
+
+#[test]
+fn it_can_require_an_object_at_the_root() {
+    let options = ParseOptions::new().root(Root::Object);
+    assert_eq!(get_error("[1, 2]", options), "Expected object at 1:1");
+}
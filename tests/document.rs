@@ -0,0 +1,94 @@
+use anyhow::Result;
+use d4t4::{Document, Element, NodeKind, TokenKind};
+use indoc::indoc;
+use std::fs;
+
+#[test]
+fn it_writes_back_identical_text() -> Result<()> {
+    let inputs = vec![
+        fs::read_to_string("tests/data/data.json")?,
+        indoc!(
+            r##"
+            # colors of the app
+            {
+                /*
+                    Look how nice, we can separate re-useable data!
+                */
+                'color' : { red: '#fed7d7' , "green":"#c6f6d5",},
+                border: "1px solid &{color.red}", // note: trailing comma
+                sizes: [ 1,2 ,3e2, -0.5 , ],
+                nothing: null,   enabled: true,
+            }
+            "##
+        )
+        .to_string(),
+        "{\r\n\t\"a\": 1\r\n}\r\n".to_string(),
+        "  'just a string'  ".to_string(),
+        "[]".to_string(),
+    ];
+    for input in inputs {
+        let document = Document::parse(&input)?;
+        assert_eq!(document.to_string(), input);
+        assert_eq!(document.to_value()?, d4t4::parse(&input)?);
+    }
+    Ok(())
+}
+
+#[test]
+fn it_keeps_comments_with_their_spans() -> Result<()> {
+    let markup = indoc!(
+        r#"{
+            // first
+            a: 1, /* second */
+        }"#
+    );
+    let document = Document::parse(markup)?;
+    let comments = document
+        .root()
+        .tokens()
+        .into_iter()
+        .filter(|token| {
+            matches!(
+                token.kind(),
+                TokenKind::LineComment | TokenKind::BlockComment
+            )
+        })
+        .map(|token| (token.text(), token.span().line, token.span().column))
+        .collect::<Vec<_>>();
+    assert_eq!(comments, vec![("// first", 2, 5), ("/* second */", 3, 11)]);
+    Ok(())
+}
+
+#[test]
+fn it_can_walk_the_tree() -> Result<()> {
+    let markup = r#"{ 'a': [1, { b: "c" }] }"#;
+    let document = Document::parse(markup)?;
+    let object = match document.root().value() {
+        Some(Element::Node(node)) => node,
+        _ => panic!("expected an object"),
+    };
+    assert_eq!(object.kind(), NodeKind::Object);
+    let member = object.members().next().unwrap();
+    assert_eq!(member.key().unwrap().text(), "'a'");
+    let array = match member.value() {
+        Some(Element::Node(node)) => node,
+        _ => panic!("expected an array"),
+    };
+    assert_eq!(array.elements().count(), 2);
+    assert_eq!(array.span().start, 7);
+    assert_eq!(array.to_string(), r#"[1, { b: "c" }]"#);
+    Ok(())
+}
+
+#[test]
+fn it_lists_every_token() -> Result<()> {
+    let document = Document::parse("{a:[1,],}")?;
+    let tokens = document
+        .root()
+        .tokens()
+        .into_iter()
+        .map(|token| format!("{:?} {}", token.kind(), token.text()))
+        .collect::<Vec<_>>();
+    insta::assert_debug_snapshot!(tokens);
+    Ok(())
+}
@@ -0,0 +1,174 @@
+use anyhow::Result;
+use d4t4::{Document, Element, Error};
+use indoc::indoc;
+use serde_json::json;
+
+#[test]
+fn it_sets_values_keeping_everything_else() -> Result<()> {
+    let mut document = Document::parse(indoc!(
+        r##"
+        {
+            // the palette
+            color: { red: '#fed7d7', /* light */ green: '#c6f6d5' },
+            sizes: [1, 2, 3],
+        }
+        "##
+    ))?;
+    document.set("color.red", "#f00")?;
+    document.set("sizes[1]", 20)?;
+    document.set("sizes[-1]", json!({ "large": true }))?;
+    assert_eq!(
+        document.to_string(),
+        indoc!(
+            r##"
+            {
+                // the palette
+                color: { red: '#f00', /* light */ green: '#c6f6d5' },
+                sizes: [1, 20, {'large': true}],
+            }
+            "##
+        )
+    );
+    match document.get("color.green") {
+        Some(Element::Token(token)) => assert_eq!(token.text(), "'#c6f6d5'"),
+        _ => panic!("expected a token"),
+    }
+    Ok(())
+}
+
+#[test]
+fn it_inserts_keys_and_values_following_the_layout() -> Result<()> {
+    let mut document = Document::parse(indoc!(
+        r#"
+        {
+            "a": 1,
+            "b": [1, 3]
+        }
+        "#
+    ))?;
+    document.insert("c", "&{a}")?;
+    document.insert("b[1]", 2)?;
+    document.insert("b[3]", 4)?;
+    document.set("d", json!([]))?;
+    document.insert("d[0]", "x")?;
+    assert_eq!(
+        document.to_string(),
+        indoc!(
+            r#"
+            {
+                "a": 1,
+                "b": [1, 2, 3, 4],
+                "c": "\&{a}",
+                "d": [ "x" ]
+            }
+            "#
+        )
+    );
+    assert_eq!(document.to_value()?["c"], "&{a}");
+    Ok(())
+}
+
+#[test]
+fn it_inserts_after_comments_on_the_line_of_the_last_value() -> Result<()> {
+    let mut document = Document::parse("{\n  a: 1, // one\n}")?;
+    document.insert("b", 2)?;
+    assert_eq!(document.to_string(), "{\n  a: 1, // one\n  b: 2,\n}");
+
+    let mut document = Document::parse("[\n  1 // one\n]")?;
+    document.insert("[1]", 2)?;
+    assert_eq!(document.to_string(), "[\n  1, // one\n  2\n]");
+    assert_eq!(document.to_value()?, json!([1, 2]));
+    Ok(())
+}
+
+#[test]
+fn it_removes_values_with_their_comma() -> Result<()> {
+    let mut document = Document::parse(indoc!(
+        r#"
+        {
+            a: 1,
+            // keep me
+            b: 2,
+            c: [1, 2, 3],
+        }
+        "#
+    ))?;
+    document.remove("b")?;
+    document.remove("c[2]")?;
+    document.remove("c[0]")?;
+    assert_eq!(
+        document.to_string(),
+        indoc!(
+            r#"
+            {
+                a: 1,
+                // keep me
+                c: [2],
+            }
+            "#
+        )
+    );
+    Ok(())
+}
+
+#[test]
+fn it_keeps_line_comments_in_front_of_removed_values() -> Result<()> {
+    let mut document = Document::parse("{\n  a: 1, // one\n  b: 2}")?;
+    document.remove("b")?;
+    assert_eq!(document.to_string(), "{\n  a: 1 // one\n}");
+    assert_eq!(document.to_value()?, json!({ "a": 1 }));
+
+    let mut document = Document::parse("[\n 1, // one\n 2]")?;
+    document.remove("[1]")?;
+    assert_eq!(document.to_string(), "[\n 1 // one\n]");
+    assert_eq!(document.to_value()?, json!([1]));
+    Ok(())
+}
+
+#[test]
+fn it_finds_keys_with_escapes() -> Result<()> {
+    let mut document = Document::parse(r#"{"a\&{b": 1, c: 2}"#)?;
+    assert!(document.get("a&{b").is_some());
+    document.set("a&{b", 5)?;
+    assert_eq!(document.to_string(), r#"{"a\&{b": 5, c: 2}"#);
+    assert_eq!(document.to_value()?, json!({ "a&{b": 5, "c": 2 }));
+    Ok(())
+}
+
+#[test]
+fn it_renames_keys_keeping_quotes() -> Result<()> {
+    let mut document = Document::parse("{ 'a': 1, b: 2 }")?;
+    document.rename_key("a", "first")?;
+    document.rename_key("b", "second key")?;
+    assert_eq!(document.to_string(), "{ 'first': 1, 'second key': 2 }");
+    document.rename_key("first", "first")?;
+    assert_eq!(document.to_string(), "{ 'first': 1, 'second key': 2 }");
+    Ok(())
+}
+
+#[test]
+fn it_inserts_into_empty_objects_and_arrays() -> Result<()> {
+    let mut document = Document::parse("{ a: {}, b: [   ], c: { /* none */ } }")?;
+    document.insert("a.x", 1)?;
+    document.insert("b[0]", 2)?;
+    document.insert("c.z", 3)?;
+    assert_eq!(
+        document.to_string(),
+        r#"{ a: { "x": 1 }, b: [ 2 ], c: { "z": 3  /* none */ } }"#
+    );
+    Ok(())
+}
+
+#[test]
+fn it_errors_on_invalid_edits() -> Result<()> {
+    let mut document = Document::parse("{ a: 1, b: [1] }")?;
+    let error = document.insert("a", 2).expect_err("key exists");
+    assert!(matches!(error, Error::Edit { .. }));
+    assert_eq!(error.to_string(), "Cannot edit a: the key already exists");
+    assert!(document.remove("c").is_err());
+    assert!(document.insert("b[5]", 1).is_err());
+    assert!(document.set("a.b", 1).is_err());
+    assert!(document.rename_key("a", "b").is_err());
+    assert_eq!(document.to_string(), "{ a: 1, b: [1] }");
+    Ok(())
+}
@@ -0,0 +1,56 @@
+#[macro_use]
+mod common;
+
+use d4t4::{parse_with_options, ParseOptions, Result};
+use indoc::indoc;
+use std::collections::HashMap;
+
+fn options() -> ParseOptions {
+    let mut variables = HashMap::new();
+    variables.insert("HOST".to_string(), "localhost".to_string());
+    variables.insert("PORT".to_string(), "5432".to_string());
+    ParseOptions::new().variables(variables)
+}
+
+#[test]
+fn it_can_parse_environment_variables() -> Result<()> {
+    let markup = indoc!(
+        r#"{
+            host: "&{env:HOST}",
+            url: "postgres://&{env:HOST}:&{env:PORT}/db",
+        }"#
+    );
+    let x = parse_with_options(markup, &options())?;
+    assert_json_snapshot!(x);
+    Ok(())
+}
+
+#[test]
+fn it_can_fall_back_when_environment_variable_is_missing() -> Result<()> {
+    let markup = indoc!(
+        r#"{
+            defaults: { user: "admin" },
+            user: "&{env:USER_NAME ?? defaults.user}",
+            password: "&{env:PASSWORD:-secret}",
+            database: "&{env:DATABASE ?? env:HOST ?? 'db'}",
+            host: "&{defaults.host ?? env:HOST}",
+        }"#
+    );
+    let x = parse_with_options(markup, &options())?;
+    assert_json_snapshot!(x);
+    Ok(())
+}
+
+#[test]
+fn it_thows_error_when_environment_variable_is_missing() {
+    let markup = indoc!(
+        r#"{
+            url: "&{env:DATABASE_URL}",
+        }"#
+    );
+    let x = parse_with_options(markup, &options()).err().unwrap();
+    assert_eq!(
+        format!("{}", x),
+        "Unresolved reference env:DATABASE_URL, DATABASE_URL was not found at 2:11"
+    );
+}
@@ -0,0 +1,354 @@
+use d4t4::{parse, parse_with_options, Error, ParseOptions, Span};
+use indoc::indoc;
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+#[test]
+fn it_reports_syntax_errors_with_position() {
+    let markup = indoc!(
+        r#"{
+            name: "Mr. Karlsson"
+            age: 42,
+        }"#
+    );
+    let x = parse(markup).err().unwrap();
+    assert!(matches!(x, Error::Syntax { .. }));
+    assert_eq!(format!("{}", x), "Expected comma at 3:5");
+    assert_eq!(x.span().line, 3);
+    assert_eq!(x.span().column, 5);
+}
+
+#[test]
+fn it_reports_duplicate_keys_with_span() {
+    let markup = indoc!(
+        r#"{
+            name: "Mr. Karlsson",
+            'name': "Mr. Eriksson",
+        }"#
+    );
+    let x = parse(markup).err().unwrap();
+    assert!(matches!(x, Error::DuplicateKey { ref key, .. } if key == "name"));
+    let span = x.span();
+    assert_eq!(&markup[span.start..span.end], "'name'");
+    assert_eq!((span.line, span.column), (3, 5));
+}
+
+#[test]
+fn it_reports_unresolved_references_with_span() {
+    let markup = indoc!(
+        r#"{
+            color: "1px solid &{colors.red}",
+        }"#
+    );
+    let x = parse(markup).err().unwrap();
+    assert!(matches!(
+        x,
+        Error::UnresolvedReference { ref target, ref missing, .. }
+            if target == "colors.red" && missing == "colors"
+    ));
+    let span = x.span();
+    assert_eq!(&markup[span.start..span.end], "&{colors.red}");
+    assert_eq!(
+        span,
+        Span {
+            start: 24,
+            end: 37,
+            line: 2,
+            column: 23
+        }
+    );
+}
+
+#[test]
+fn it_reports_invalid_escapes() {
+    let markup = indoc!(
+        r#"{
+            surrogate: "\uD800",
+        }"#
+    );
+    let x = parse(markup).err().unwrap();
+    assert!(matches!(x, Error::InvalidEscape { ref escape, .. } if escape == "\\uD800"));
+    assert_eq!((x.span().line, x.span().column), (2, 17));
+}
+
+#[test]
+fn it_reports_cyclic_references() {
+    let markup = indoc!(
+        r#"{
+            a: "&{b}",
+            b: "&{a}",
+        }"#
+    );
+    let x = parse(markup).err().unwrap();
+    assert!(matches!(x, Error::CyclicReference { ref cycle, .. } if cycle == &["a", "b", "a"]));
+}
+
+#[test]
+fn it_reports_errors_in_imported_files() {
+    let mut files = HashMap::new();
+    files.insert(PathBuf::from("colors.d4t4"), "{ red: }".to_string());
+    let options = ParseOptions::new().files(files);
+    let markup = indoc!(
+        r#"{
+            red: "&{file:colors.d4t4#red}",
+        }"#
+    );
+    let x = parse_with_options(markup, &options).err().unwrap();
+    match x {
+        Error::Import { path, span, source } => {
+            assert_eq!(path, "colors.d4t4");
+            assert_eq!((span.line, span.column), (2, 11));
+            assert!(matches!(*source, Error::Syntax { .. }));
+            assert_eq!(source.span().column, 8);
+        }
+        _ => panic!("expected an import error"),
+    }
+}
+
+#[test]
+fn it_suggests_similar_references() {
+    let markup = indoc!(
+        r##"{
+            color: {
+                red: "#fed7d7",
+            },
+            border: "1px solid &{color.rde}",
+        }"##
+    );
+    let x = parse(markup).err().unwrap();
+    assert!(matches!(
+        x,
+        Error::UnresolvedReference { ref suggestion, .. } if suggestion.as_deref() == Some("color.red")
+    ));
+}
+
+#[test]
+fn it_renders_diagnostics() {
+    let markup = indoc!(
+        r##"{
+            color: {
+                red: "#fed7d7",
+            },
+            border: "1px solid &{color.rde}",
+        }"##
+    );
+    let x = parse(markup).err().unwrap();
+    insta::assert_snapshot!(x.diagnostic().render(markup));
+}
+
+#[test]
+fn it_renders_diagnostics_with_ansi_colors() {
+    let markup = "{ a: 1 b: 2 }";
+    let x = parse(markup).err().unwrap();
+    let rendered = x.diagnostic().render_ansi(markup);
+    assert!(rendered.starts_with("\u{1b}[1;31merror"));
+    assert!(rendered.contains("Expected comma"));
+}
+
+#[test]
+fn it_renders_diagnostics_of_imported_files() {
+    let mut files = HashMap::new();
+    files.insert(PathBuf::from("colors.d4t4"), "{ red: 'red' }".to_string());
+    let options = ParseOptions::new().files(files);
+    let markup = indoc!(
+        r#"{
+            red: "&{file:colors.d4t4#rd}",
+        }"#
+    );
+    let x = parse_with_options(markup, &options).err().unwrap();
+    insta::assert_snapshot!(x.diagnostic().render(markup));
+}
+
+#[test]
+fn it_renders_diagnostics_of_errors_without_span() {
+    struct Failing;
+    impl std::io::Read for Failing {
+        fn read(&mut self, _: &mut [u8]) -> std::io::Result<usize> {
+            Err(std::io::Error::other("disconnected"))
+        }
+    }
+    let mut document = d4t4::Document::parse("{ a: 1 }").unwrap();
+    let mut keys = HashMap::new();
+    keys.insert(vec![1], 1);
+    let errors = vec![
+        document.set("b.c", 2).unwrap_err(),
+        d4t4::from_reader::<_, u8>(Failing).unwrap_err(),
+        d4t4::to_string(&keys).unwrap_err(),
+    ];
+    for x in errors {
+        assert!(x.diagnostic().labels.is_empty());
+        let rendered = x.diagnostic().render("{ a: 1 }");
+        assert_eq!(rendered, format!("error: {}\n", x.diagnostic().message));
+        assert!(x
+            .diagnostic()
+            .render_ansi("")
+            .contains(&x.diagnostic().message));
+    }
+}
+
+#[test]
+fn it_can_collect_all_errors() {
+    let markup = indoc!(
+        r#"{
+            name: "Mr. Karlsson",
+            name: "Mr. Eriksson",
+            surrogate: "\uD800",
+            color: "&{colors.red}",
+            size: "&{sizes.m}",
+        }"#
+    );
+    let options = ParseOptions::new().recover(true);
+    let x = parse_with_options(markup, &options).err().unwrap();
+    let errors = x
+        .errors()
+        .iter()
+        .map(|error| error.to_string())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        errors,
+        vec![
+            "Object already contains key: name at 3:5",
+            "Invalid escape \\uD800 at 4:17",
+            "Unresolved reference colors.red, colors was not found at 5:13",
+            "Unresolved reference sizes.m, sizes was not found at 6:12",
+        ]
+    );
+}
+
+#[test]
+fn it_can_recover_from_syntax_errors() {
+    let markup = indoc!(
+        r#"{
+            name: "Mr. Karlsson"
+            age: 42,
+            height: ,
+            width: tru,
+            color: "&{colors.red}",
+        }"#
+    );
+    let options = ParseOptions::new().recover(true);
+    let x = parse_with_options(markup, &options).err().unwrap();
+    let errors = x
+        .errors()
+        .iter()
+        .map(|error| error.to_string())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        errors,
+        vec![
+            "Expected comma at 3:5",
+            "Expected object, array, null, bool, number or string at 4:13",
+            "Expected object, array, null, bool, number or string at 5:12",
+            "Unresolved reference colors.red, colors was not found at 6:13",
+        ]
+    );
+}
+
+#[test]
+fn it_reports_invalid_values_once_when_recovering() {
+    let options = ParseOptions::new().recover(true);
+    for markup in &["{a: @, b: 1}", "[1, @, 3]"] {
+        let x = parse_with_options(markup, &options).err().unwrap();
+        let errors = x
+            .errors()
+            .iter()
+            .map(|error| error.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            errors,
+            vec!["Expected object, array, null, bool, number or string at 1:5"]
+        );
+    }
+}
+
+#[test]
+fn it_can_recover_from_invalid_escapes() {
+    let markup = indoc!(
+        r#"{
+            name: "Mr. \Karlsson",
+            color: "&{colors.red}"
+            size: 42,
+        }"#
+    );
+    let options = ParseOptions::new().recover(true);
+    let x = parse_with_options(markup, &options).err().unwrap();
+    let errors = x
+        .errors()
+        .iter()
+        .map(|error| error.to_string())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        errors,
+        vec![
+            "Invalid escape \\K at 2:16",
+            "Unresolved reference colors.red, colors was not found at 3:13",
+            "Expected comma at 4:5",
+        ]
+    );
+}
+
+#[test]
+fn it_keeps_the_errors_when_the_input_ends_early() {
+    let markup = indoc!(
+        r#"{
+            color: "&{colors.red}",
+            sizes: [1, "2"#
+    );
+    let options = ParseOptions::new().recover(true);
+    let x = parse_with_options(markup, &options).err().unwrap();
+    let errors = x
+        .errors()
+        .iter()
+        .map(|error| error.to_string())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        errors,
+        vec![
+            "Unresolved reference colors.red, colors was not found at 2:9",
+            "Unterminated string at 3:12",
+        ]
+    );
+}
+
+#[test]
+fn it_names_what_is_expected_like_in_the_document() {
+    let messages = ["{ : 1 }", "{ a: 1 } b", "{ a: \"&{}\" }"]
+        .iter()
+        .map(|markup| parse(markup).err().unwrap().to_string())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        messages,
+        vec![
+            "Expected key at 1:3",
+            "Expected end of input at 1:10",
+            "Invalid reference at 1:7",
+        ]
+    );
+}
+
+#[test]
+fn it_only_reports_the_first_error_without_recovery() {
+    let markup = indoc!(
+        r#"{
+            name: "Mr. Karlsson",
+            name: "Mr. Eriksson",
+            color: "&{colors.red}",
+        }"#
+    );
+    let x = parse(markup).err().unwrap();
+    assert!(matches!(x, Error::DuplicateKey { .. }));
+    assert_eq!(x.errors().len(), 1);
+}
+
+#[test]
+fn it_can_recover_without_errors() -> anyhow::Result<()> {
+    let markup = indoc!(
+        r#"{
+            size: 42,
+            width: "&{size}",
+        }"#
+    );
+    let options = ParseOptions::new().recover(true);
+    assert_eq!(parse_with_options(markup, &options)?, parse(markup)?);
+    Ok(())
+}
@@ -0,0 +1,134 @@
+#[macro_use]
+mod common;
+
+use d4t4::{parse_with_options, ParseOptions, Result};
+use indoc::indoc;
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+fn options(files: &[(&str, &str)]) -> ParseOptions {
+    let files = files
+        .iter()
+        .map(|(path, input)| (PathBuf::from(path), input.to_string()))
+        .collect::<HashMap<_, _>>();
+    ParseOptions::new().files(files).path("tokens/main.d4t4")
+}
+
+#[test]
+fn it_can_reference_other_files() -> Result<()> {
+    let colors = indoc!(
+        r##"{
+            red: "#fed7d7",
+            shades: ["#fff", "#000"],
+        }"##
+    );
+    let markup = indoc!(
+        r#"{
+            border: "1px solid &{file:./colors.d4t4#red}",
+            black: "&{file:colors.d4t4#shades[-1]}",
+            colors: "&{file:./colors.d4t4}",
+        }"#
+    );
+    let options = options(&[("tokens/colors.d4t4", colors)]);
+    let x = parse_with_options(markup, &options)?;
+    assert_json_snapshot!(x);
+    Ok(())
+}
+
+#[test]
+fn it_resolves_files_relative_to_the_referencing_file() -> Result<()> {
+    let base = indoc!(
+        r#"{
+            size: "4px",
+        }"#
+    );
+    let spacing = indoc!(
+        r#"{
+            base: "&{file:../base.d4t4#size}",
+            double: "calc(&{.base} * 2)",
+        }"#
+    );
+    let markup = indoc!(
+        r#"{
+            padding: "&{file:./spacing/index.d4t4#double}",
+        }"#
+    );
+    let options = options(&[
+        ("tokens/base.d4t4", base),
+        ("tokens/spacing/index.d4t4", spacing),
+    ]);
+    let x = parse_with_options(markup, &options)?;
+    assert_eq!(x["padding"], "calc(4px * 2)");
+    Ok(())
+}
+
+#[test]
+fn it_can_fall_back_when_file_is_missing() -> Result<()> {
+    let markup = indoc!(
+        r#"{
+            accent: "&{file:./overrides.d4t4#accent ?? 'blue'}",
+        }"#
+    );
+    let x = parse_with_options(markup, &options(&[]))?;
+    assert_eq!(x["accent"], "blue");
+    Ok(())
+}
+
+#[test]
+fn it_thows_error_when_file_is_missing() {
+    let markup = indoc!(
+        r#"{
+            accent: "&{file:./overrides.d4t4#accent}",
+        }"#
+    );
+    let x = parse_with_options(markup, &options(&[])).err().unwrap();
+    assert_eq!(
+        format!("{}", x),
+        "Unresolved reference file:./overrides.d4t4#accent, ./overrides.d4t4 was not found at 2:14"
+    );
+}
+
+#[test]
+fn it_thows_error_when_key_is_not_found_in_file() {
+    let colors = indoc!(
+        r##"{
+            red: "#fed7d7",
+        }"##
+    );
+    let markup = indoc!(
+        r#"{
+            accent: "&{file:./colors.d4t4#blue}",
+        }"#
+    );
+    let options = options(&[("tokens/colors.d4t4", colors)]);
+    let x = parse_with_options(markup, &options).err().unwrap();
+    assert_eq!(
+        format!("{}", x),
+        "Unresolved reference file:./colors.d4t4#blue, blue was not found at 2:14"
+    );
+}
+
+#[test]
+fn it_thows_error_on_cyclic_imports() {
+    let a = indoc!(
+        r#"{
+            b: "&{file:./b.d4t4#value}",
+        }"#
+    );
+    let b = indoc!(
+        r#"{
+            value: "&{file:./main.d4t4#a}",
+        }"#
+    );
+    let markup = indoc!(
+        r#"{
+            a: "&{file:./a.d4t4#b}",
+        }"#
+    );
+    let options = options(&[("tokens/a.d4t4", a), ("tokens/b.d4t4", b)]);
+    let x = parse_with_options(markup, &options).err().unwrap();
+    assert_eq!(
+        format!("{}", x),
+        "Cyclic import: tokens/main.d4t4 -> tokens/a.d4t4 -> tokens/b.d4t4 -> tokens/main.d4t4 at 2:13"
+    );
+}
@@ -0,0 +1,90 @@
+use anyhow::Result;
+use d4t4::{format, is_formatted, parse};
+use indoc::indoc;
+use std::fs;
+use std::process::Command;
+
+const UNFORMATTED: &str = indoc!(
+    r##"
+    # colors of the app
+    {'color' : { red: '#fed7d7' , "green":"#c6f6d5",}, // the palette
+
+
+      /* sizes */ "sizes": [ 1,2 ,3e2, -0.5 ],
+        'quoted key': "it's \"quoted\"",
+        objects: [{border: "1px solid &{color.red}"}],
+      empty: { },
+      # the end
+    }
+    "##
+);
+
+#[test]
+fn it_formats_documents() -> Result<()> {
+    let formatted = format(UNFORMATTED)?;
+    insta::assert_snapshot!(formatted);
+    assert_eq!(parse(&formatted)?, parse(UNFORMATTED)?);
+    Ok(())
+}
+
+#[test]
+fn it_is_idempotent() -> Result<()> {
+    let inputs = vec![
+        UNFORMATTED.to_string(),
+        fs::read_to_string("tests/data/data.json")?,
+        "'just a string' // really\n".to_string(),
+        "[/* empty */]".to_string(),
+    ];
+    for input in inputs {
+        let formatted = format(&input)?;
+        assert!(is_formatted(&formatted)?, "{}", formatted);
+        assert_eq!(parse(&formatted)?, parse(&input)?);
+    }
+    Ok(())
+}
+
+#[test]
+fn it_unquotes_keys_that_are_identifiers() -> Result<()> {
+    let formatted = format(r#"{ "a\u0062": 1, 'c\&{d': 2 }"#)?;
+    assert_eq!(formatted, "{\n    ab: 1,\n    \"c\\&{d\": 2,\n}\n");
+    assert_eq!(parse(&formatted)?["c&{d"], 2);
+    Ok(())
+}
+
+#[test]
+fn it_breaks_long_arrays() -> Result<()> {
+    let numbers = (0..30).map(|x| x.to_string()).collect::<Vec<_>>();
+    let formatted = format(&format!("{{ numbers: [{}] }}", numbers.join(",")))?;
+    assert_eq!(formatted.lines().count(), 34);
+    assert!(!is_formatted("{ a: 1 }")?);
+    assert!(is_formatted("{\n    a: 1,\n}\n")?);
+    Ok(())
+}
+
+#[test]
+fn it_formats_files_from_the_command_line() -> Result<()> {
+    let path = std::env::temp_dir().join("d4t4-format-test.d4t4");
+    fs::write(&path, UNFORMATTED)?;
+    let run = |args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_d4t4"))
+            .arg("fmt")
+            .args(args)
+            .arg(&path)
+            .output()
+    };
+
+    let output = run(&["--check"])?;
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(fs::read_to_string(&path)?, UNFORMATTED);
+
+    assert!(run(&[])?.status.success());
+    assert_eq!(fs::read_to_string(&path)?, format(UNFORMATTED)?);
+    assert!(run(&["--check"])?.status.success());
+
+    fs::write(&path, "{ a: }")?;
+    let output = run(&["--check"])?;
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8(output.stderr)?.starts_with("error: "));
+    fs::remove_file(&path)?;
+    Ok(())
+}
@@ -1,11 +1,12 @@
-use d4t4::{parse, Result};
+use anyhow::Result;
+use d4t4::parse;
 use serde_json::Value;
 use std::fs::File;
 use std::io::prelude::*;
 
 // https://github.com/pest-parser/pest/blob/master/grammars/tests/examples.json
 
-const JSON: &'static str = "tests/data/data.json";
+const JSON: &str = "tests/data/data.json";
 
 #[test]
 fn it_can_parse_json() -> Result<()> {
@@ -0,0 +1,72 @@
+use anyhow::Result;
+use d4t4::{from_str, parse_spanned};
+
+#[test]
+fn it_reads_numbers_as_128_bit_integers() -> Result<()> {
+    assert_eq!(from_str::<i128>("-42")?, -42);
+    assert_eq!(
+        from_str::<u128>("18446744073709551615")?,
+        u128::from(u64::MAX)
+    );
+    let error = from_str::<u128>("{ a: 1 }").unwrap_err();
+    assert_eq!(error.to_string(), "invalid type: map, expected u128 at 1:1");
+
+    let value = parse_spanned("[-7, 7, 1.5, 'x']")?;
+    let numbers = value.value.as_array().unwrap();
+    assert_eq!(numbers[0].value.as_i128(), Some(-7));
+    assert_eq!(numbers[0].value.as_u128(), None);
+    assert_eq!(numbers[1].value.as_u128(), Some(7));
+    assert_eq!(numbers[2].value.as_i128(), None);
+    assert_eq!(numbers[3].value.as_i128(), None);
+    Ok(())
+}
+
+#[cfg(feature = "arbitrary_precision")]
+#[test]
+fn it_keeps_numbers_as_they_are_written() -> Result<()> {
+    use d4t4::{parse, to_string};
+    use serde::Deserialize;
+
+    let input = "{ id: 1234567890123456789012345, price: 19.999999999999999999, big: -1e400 }";
+    let value = parse(input)?;
+    assert_eq!(value["id"].to_string(), "1234567890123456789012345");
+    assert_eq!(value["price"].to_string(), "19.999999999999999999");
+    assert_eq!(
+        to_string(&value)?,
+        r#"{"id":1234567890123456789012345,"price":19.999999999999999999,"big":-1e400}"#
+    );
+
+    let spanned = parse_spanned(input)?;
+    let id = spanned.value.get("id").unwrap();
+    assert_eq!(id.value.as_u128(), Some(1234567890123456789012345));
+    assert_eq!(id.value.as_i128(), Some(1234567890123456789012345));
+
+    #[derive(Deserialize)]
+    struct Order {
+        id: u128,
+        price: serde_json::Number,
+        big: serde_json::Value,
+    }
+    let order: Order = from_str(input)?;
+    assert_eq!(order.id, 1234567890123456789012345);
+    assert_eq!(order.price.to_string(), "19.999999999999999999");
+    assert_eq!(order.big.to_string(), "-1e400");
+    assert_eq!(from_str::<f64>("19.999999999999999999")?, 20.0);
+    assert_eq!(
+        from_str::<serde_json::Value>("[0.5, 2]")?.to_string(),
+        "[0.5,2]"
+    );
+    assert!(from_str::<i128>("340282366920938463463374607431768211456").is_err());
+    Ok(())
+}
+
+#[test]
+fn it_rejects_floats_beyond_f64() {
+    #[derive(serde::Deserialize, Debug)]
+    struct Size {
+        #[allow(dead_code)]
+        f: f64,
+    }
+    let error = from_str::<Size>("{\n    f: 1e400,\n}").unwrap_err();
+    assert_eq!(error.span().line, 2);
+}
@@ -1,6 +1,8 @@
+#[macro_use]
+mod common;
+
 use d4t4::{parse, Result};
 use indoc::indoc;
-use insta::assert_json_snapshot;
 
 #[test]
 fn it_can_parse_different_keys() -> Result<()> {
@@ -29,5 +31,26 @@ fn it_errors_on_same_key() {
         }"#
     );
     let x = parse(markup).err().unwrap();
-    assert_eq!(format!("{}", x), "Object already contains key: name");
+    assert_eq!(format!("{}", x), "Object already contains key: name at 3:5");
+}
+
+#[cfg(feature = "preserve_order")]
+#[test]
+fn it_keeps_the_order_of_keys() -> Result<()> {
+    use d4t4::{parse_with_options, to_string, DuplicateKeys, ParseOptions};
+
+    let markup = "{ zebra: 1, apple: { y: 2, x: 3 }, mango: '&{apple}' }";
+    let x = parse(markup)?;
+    let keys: Vec<_> = x.as_object().unwrap().keys().collect();
+    assert_eq!(keys, ["zebra", "apple", "mango"]);
+    assert_eq!(
+        to_string(&x)?,
+        r#"{"zebra":1,"apple":{"y":2,"x":3},"mango":{"y":2,"x":3}}"#
+    );
+
+    // A key that is given again keeps its first position
+    let options = ParseOptions::new().duplicate_keys(DuplicateKeys::Last);
+    let x = parse_with_options("{ b: 1, a: 2, b: 3 }", &options)?;
+    assert_eq!(to_string(&x)?, r#"{"b":3,"a":2}"#);
+    Ok(())
 }
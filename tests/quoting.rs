@@ -1,6 +1,8 @@
+#[macro_use]
+mod common;
+
 use d4t4::{parse, Result};
 use indoc::indoc;
-use insta::assert_json_snapshot;
 
 #[test]
 fn it_can_parse_double_quotes() -> Result<()> {
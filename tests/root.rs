@@ -0,0 +1,49 @@
+use anyhow::Result;
+use d4t4::{parse, parse_with_options, ParseOptions, Root};
+use serde_json::json;
+use std::collections::HashMap;
+
+#[test]
+fn it_can_parse_scalar_roots() -> Result<()> {
+    assert_eq!(parse("'Mr. Karlsson'")?, json!("Mr. Karlsson"));
+    assert_eq!(parse(" 42 ")?, json!(42));
+    assert_eq!(parse("-1.5e3")?.as_f64(), Some(-1500.0));
+    assert_eq!(parse("true")?, json!(true));
+    assert_eq!(parse("null // nothing\n")?, json!(null));
+    Ok(())
+}
+
+#[test]
+fn it_can_reference_variables_and_files_from_a_string_root() -> Result<()> {
+    let mut variables = HashMap::new();
+    variables.insert("HOST".to_string(), "localhost".to_string());
+    let options = ParseOptions::new().variables(variables);
+    let json = parse_with_options(r#""http://&{env:HOST}:&{env:PORT:-8080}""#, &options)?;
+    assert_eq!(json, json!("http://localhost:8080"));
+    Ok(())
+}
+
+#[test]
+fn it_rejects_paths_in_a_string_root() {
+    let x = parse(r#""&{color}""#).err().unwrap();
+    assert_eq!(
+        x.to_string(),
+        "Reference color needs an object or array at the root at 1:2"
+    );
+}
+
+#[test]
+fn it_can_reference_elements_of_an_array_root() -> Result<()> {
+    assert_eq!(
+        parse(r#"["red", "&{[0]}", "&{[1]}"]"#)?,
+        json!(["red", "red", "red"])
+    );
+    Ok(())
+}
+
+#[test]
+fn it_can_require_an_object_or_array_at_the_root() {
+    let options = ParseOptions::new().root(Root::ObjectOrArray);
+    let x = parse_with_options("42", &options).err().unwrap();
+    assert_eq!(x.to_string(), "Expected object or array at 1:1");
+}
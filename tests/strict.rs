@@ -0,0 +1,71 @@
+use anyhow::Result;
+use d4t4::{parse_with_options, ParseOptions};
+use indoc::indoc;
+use std::fs;
+
+#[test]
+fn it_accepts_plain_json() -> Result<()> {
+    let data = fs::read_to_string("tests/data/data.json")?;
+    let v: serde_json::Value = serde_json::from_str(&data)?;
+    assert_eq!(parse_with_options(&data, &ParseOptions::strict())?, v);
+    Ok(())
+}
+
+#[test]
+fn it_accepts_surrogate_pairs() -> Result<()> {
+    let markup = r#"{ "a": "\ud83d\ude00 \u00e9" }"#;
+    let x = parse_with_options(markup, &ParseOptions::strict())?;
+    assert_eq!(x["a"], "\u{1f600} \u{e9}");
+    assert!(parse_with_options(r#"{ "a": "\ud83d" }"#, &ParseOptions::strict()).is_err());
+    assert!(parse_with_options(r#"{ "a": "\ude00\ud83d" }"#, &ParseOptions::strict()).is_err());
+    Ok(())
+}
+
+#[test]
+fn it_keeps_references_as_text() -> Result<()> {
+    let markup = r#"{ "a": 1, "b": "&{a}" }"#;
+    let x = parse_with_options(markup, &ParseOptions::strict())?;
+    assert_eq!(x["b"], "&{a}");
+    Ok(())
+}
+
+#[test]
+fn it_names_every_extension_that_is_used() {
+    let markup = indoc!(
+        r##"{
+            // colors
+            red: '#fed7d7',
+            "green": "#c6f6d5",
+            "blue": "&{green}", /* same */
+            "escape": "\&{green} \'",
+            "tab": "	",
+        }"##
+    );
+    let options = ParseOptions::strict().recover(true);
+    let x = parse_with_options(markup, &options).err().unwrap();
+    let errors = x
+        .errors()
+        .iter()
+        .map(|error| error.to_string())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        errors,
+        vec![
+            "Unsupported line comment at 2:5",
+            "Unsupported unquoted key at 3:5",
+            "Unsupported single-quoted string at 3:10",
+            "Unsupported block comment at 5:25",
+            "Unsupported reference at 6:16",
+            "Unsupported escaped single quote at 6:26",
+            "Unsupported unescaped control character at 7:13",
+            "Unsupported trailing comma at 7:15",
+        ]
+    );
+}
+
+#[test]
+fn it_rejects_quotes_of_the_string_inside_of_references() {
+    let markup = r#"{"c": "&{a ?? "x"}"}"#;
+    assert!(serde_json::from_str::<serde_json::Value>(markup).is_err());
+    assert!(parse_with_options(markup, &ParseOptions::strict()).is_err());
+}
@@ -0,0 +1,160 @@
+use anyhow::Result;
+use d4t4::value::{Quote, Value};
+use d4t4::{parse_spanned, parse_spanned_with_options, DuplicateKeys, ParseOptions};
+use indoc::indoc;
+use serde_json::json;
+
+#[test]
+fn it_keeps_spans_and_key_order() -> Result<()> {
+    let value = parse_spanned(indoc!(
+        r#"
+        {
+            zebra: 1,
+            "apple": [true, null],
+            'mango': { x: 'y' },
+        }
+        "#
+    ))?;
+    let members = value.value.as_object().unwrap();
+    let keys: Vec<_> = members.iter().map(|m| m.key.value.name.as_str()).collect();
+    assert_eq!(keys, ["zebra", "apple", "mango"]);
+    let quotes: Vec<_> = members.iter().map(|m| m.key.value.quote).collect();
+    assert_eq!(quotes, [Quote::None, Quote::Double, Quote::Single]);
+    assert_eq!(
+        (members[1].key.span.line, members[1].key.span.column),
+        (3, 5)
+    );
+
+    let apple = value.value.get("apple").unwrap();
+    assert_eq!((apple.span.line, apple.span.column), (3, 14));
+    let null = &apple.value.as_array().unwrap()[1];
+    assert_eq!(null.value, Value::Null);
+    assert_eq!((null.span.line, null.span.column), (3, 21));
+
+    let y = value.value.get("mango").unwrap().value.get("x").unwrap();
+    assert_eq!(y.value, Value::String("y".to_string(), Quote::Single));
+    assert_eq!((value.span.line, value.span.column), (1, 1));
+    Ok(())
+}
+
+#[test]
+fn it_keeps_comments() -> Result<()> {
+    let value = parse_spanned(indoc!(
+        r#"
+        // the colors
+        {
+            /* primary */
+            red: '#f00', // warm
+            blue: '#00f',
+            // the end
+            empty: [
+                // nothing yet
+            ],
+        } # done
+        "#
+    ))?;
+    let text = |comments: &[d4t4::value::Comment]| -> Vec<String> {
+        comments.iter().map(|c| c.text.clone()).collect()
+    };
+    assert_eq!(text(&value.comments.leading), ["// the colors"]);
+    assert_eq!(text(&value.comments.trailing), ["# done"]);
+
+    let red = value.value.get("red").unwrap();
+    assert_eq!(text(&red.comments.leading), ["/* primary */"]);
+    assert_eq!(text(&red.comments.trailing), ["// warm"]);
+    assert_eq!(red.comments.leading[0].span.line, 3);
+
+    let blue = value.value.get("blue").unwrap();
+    assert!(blue.comments.leading.is_empty() && blue.comments.trailing.is_empty());
+
+    let empty = value.value.get("empty").unwrap();
+    assert_eq!(text(&empty.comments.leading), ["// the end"]);
+    assert_eq!(text(&empty.comments.trailing), ["// nothing yet"]);
+    Ok(())
+}
+
+#[test]
+fn it_spans_referenced_values_by_the_reference() -> Result<()> {
+    let value = parse_spanned("{ size: 12, font: { size: '&{size}' }, copy: '&{font}' }")?;
+    let size = value.value.get("font").unwrap().value.get("size").unwrap();
+    assert_eq!(size.value, Value::Number(12.into()));
+    assert_eq!(size.span.column, 27);
+
+    let copy = value.value.get("copy").unwrap();
+    let copied = copy.value.get("size").unwrap();
+    assert_eq!(copied.value, Value::Number(12.into()));
+    assert_eq!(copied.span, copy.span);
+    Ok(())
+}
+
+#[test]
+fn it_follows_the_duplicate_key_option() -> Result<()> {
+    let input = "{ a: 1, b: 2, a: 3 }";
+    assert!(parse_spanned(input).is_err());
+
+    let options = ParseOptions::new().duplicate_keys(DuplicateKeys::Last);
+    let value = parse_spanned_with_options(input, &options)?;
+    assert_eq!(value.value.to_string(), r#"{"a":3,"b":2}"#);
+    assert_eq!(value.value.get("a").unwrap().span.column, 18);
+
+    let options = ParseOptions::new().duplicate_keys(DuplicateKeys::First);
+    let value = parse_spanned_with_options(input, &options)?;
+    assert_eq!(value.value.get("a").unwrap().span.column, 6);
+    Ok(())
+}
+
+#[test]
+fn it_converts_to_and_from_serde_json() -> Result<()> {
+    let input = "{ a: [1, 'two', { b: null }], c: -1.5e3 }";
+    let value = parse_spanned(input)?;
+    assert_eq!(d4t4::Value::from(value), d4t4::parse(input)?);
+
+    let json = json!({ "x": [true, "y"] });
+    let value = Value::from(json.clone());
+    assert_eq!(value.get("x").unwrap().value.as_array().unwrap().len(), 2);
+    assert_eq!(serde_json::Value::from(value), json);
+    Ok(())
+}
+
+#[test]
+fn it_decodes_keys_like_the_parser() -> Result<()> {
+    let value = parse_spanned(r#"{ "a\&{b": 1, 'c\'d': 2 }"#)?;
+    let keys: Vec<_> = value
+        .value
+        .as_object()
+        .unwrap()
+        .iter()
+        .map(|m| m.key.value.name.as_str())
+        .collect();
+    assert_eq!(keys, ["a&{b", "c'd"]);
+    assert_eq!(
+        value.value.get("a&{b").unwrap().value,
+        Value::Number(1.into())
+    );
+    Ok(())
+}
+
+#[test]
+fn it_keeps_the_duplicate_key_that_the_parser_keeps() -> Result<()> {
+    let input = "{\n    a: [1], // first\n    b: 2,\n    a: { c: 1 }, // last\n}";
+    let options = ParseOptions::new().duplicate_keys(DuplicateKeys::Last);
+    let value = parse_spanned_with_options(input, &options)?;
+    let a = value.value.get("a").unwrap();
+    assert_eq!(a.value.to_string(), r#"{"c":1}"#);
+    assert_eq!((a.span.line, a.span.column), (4, 8));
+    let comments: Vec<_> = a
+        .comments
+        .trailing
+        .iter()
+        .map(|c| c.text.as_str())
+        .collect();
+    assert_eq!(comments, ["// first", "// last"]);
+    assert_eq!(value.value.as_object().unwrap().len(), 2);
+
+    let options = ParseOptions::new().duplicate_keys(DuplicateKeys::First);
+    let value = parse_spanned_with_options(input, &options)?;
+    let a = value.value.get("a").unwrap();
+    assert_eq!(a.value.to_string(), "[1]");
+    assert_eq!((a.span.line, a.span.column), (2, 8));
+    Ok(())
+}
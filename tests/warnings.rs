@@ -0,0 +1,97 @@
+use anyhow::Result;
+use d4t4::{parse_with_warnings, ParseOptions, Warning};
+use indoc::indoc;
+
+#[test]
+fn it_has_no_warnings_when_all_definitions_are_used() -> Result<()> {
+    let markup = indoc!(
+        r#"{
+            color: {
+                red: 'red',
+                green: 'green',
+            },
+            objects: [
+                { border: "1px solid &{color.red}" },
+                { border: "1px solid &{color.green}" },
+            ],
+        }"#
+    );
+    let (_, warnings) = parse_with_warnings(markup, &ParseOptions::new())?;
+    assert_eq!(warnings, vec![]);
+    Ok(())
+}
+
+#[test]
+fn it_warns_about_unused_definitions() -> Result<()> {
+    let markup = indoc!(
+        r#"{
+            color: {
+                red: 'red',
+                green: 'green',
+            },
+            border: "1px solid &{color.red}",
+        }"#
+    );
+    let (_, warnings) = parse_with_warnings(markup, &ParseOptions::new())?;
+    assert_eq!(warnings.len(), 1);
+    assert_eq!(
+        warnings[0].to_string(),
+        "color.green is never referenced while other values of its object are, at 4:9"
+    );
+    Ok(())
+}
+
+#[test]
+fn it_warns_about_shadowed_keys() -> Result<()> {
+    let markup = indoc!(
+        r##"{
+            "color.red": "red",
+            color: {
+                red: "#f00",
+            },
+        }"##
+    );
+    let (_, warnings) = parse_with_warnings(markup, &ParseOptions::new())?;
+    assert!(matches!(
+        warnings.as_slice(),
+        [Warning::ShadowedKey { key, .. }] if key == "color.red"
+    ));
+    Ok(())
+}
+
+#[test]
+fn it_warns_about_empty_references() -> Result<()> {
+    let markup = indoc!(
+        r#"{
+            prefix: "",
+            name: "&{prefix}name",
+        }"#
+    );
+    let (value, warnings) = parse_with_warnings(markup, &ParseOptions::new())?;
+    assert_eq!(value["name"], "name");
+    assert_eq!(
+        warnings,
+        vec![Warning::EmptyReference {
+            target: "prefix".to_string(),
+            span: warnings[0].span(),
+        }]
+    );
+    assert_eq!(warnings[0].span().line, 3);
+    Ok(())
+}
+
+#[test]
+fn it_accepts_both_ways_of_indexing_arrays() -> Result<()> {
+    let markup = indoc!(
+        r#"{
+            colors: ["red", "green"],
+            first: "&{colors.0}",
+            last: "&{colors[-1]}",
+        }"#
+    );
+    let (value, warnings) = parse_with_warnings(markup, &ParseOptions::new())?;
+    assert_eq!(value["first"], "red");
+    assert_eq!(value["last"], "green");
+    assert_eq!(warnings, vec![]);
+    Ok(())
+}
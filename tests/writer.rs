@@ -0,0 +1,209 @@
+use anyhow::Result;
+use d4t4::{
+    from_str, parse, to_string, to_string_pretty, to_string_with_options, to_writer, WriteOptions,
+};
+use indoc::indoc;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::BTreeMap;
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct Config {
+    name: String,
+    port: u16,
+    ratio: f32,
+    tags: Vec<String>,
+    modes: Vec<Mode>,
+    limits: BTreeMap<u32, Option<char>>,
+    empty: (),
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+enum Mode {
+    Debug,
+    Level(u8),
+    Range(i32, i32),
+    Release { optimize: bool },
+}
+
+#[test]
+fn it_writes_plain_json_by_default() -> Result<()> {
+    let value = json!({ "a": [1, 2.5, -3e300], "b": { "c": null, "d": true }, "e": {}, "f": [] });
+    let written = to_string(&value)?;
+    assert_eq!(
+        written,
+        r#"{"a":[1,2.5,-3e300],"b":{"c":null,"d":true},"e":{},"f":[]}"#
+    );
+    assert_eq!(serde_json::from_str::<serde_json::Value>(&written)?, value);
+    Ok(())
+}
+
+#[test]
+fn it_writes_the_shortest_floats() -> Result<()> {
+    assert_eq!(to_string(&0.1f32)?, "0.1");
+    assert_eq!(to_string(&0.1f64)?, "0.1");
+    assert_eq!(to_string(&[1.0f32, 3.4e38, f32::NAN])?, "[1.0,3.4e38,null]");
+    assert_eq!(to_string(&[f64::INFINITY])?, "[null]");
+    Ok(())
+}
+
+#[test]
+fn it_writes_pretty() -> Result<()> {
+    let value = json!({ "color": { "red": "#f00" }, "empty": [], "sizes": [1, [2]] });
+    assert_eq!(
+        to_string_pretty(&value)?,
+        indoc!(
+            r##"
+            {
+                "color": {
+                    "red": "#f00"
+                },
+                "empty": [],
+                "sizes": [
+                    1,
+                    [
+                        2
+                    ]
+                ]
+            }"##
+        )
+    );
+    Ok(())
+}
+
+#[test]
+fn it_writes_with_options() -> Result<()> {
+    let value = json!({ "2x": [3], "_private": 2, "name": "Karlsson's", "two words": 1 });
+    let options = WriteOptions::new()
+        .indent("\t")
+        .unquoted_keys(true)
+        .quote('\'')
+        .trailing_commas(true);
+    assert_eq!(
+        to_string_with_options(&value, &options)?,
+        "{\n\t2x: [\n\t\t3,\n\t],\n\t'_private': 2,\n\tname: 'Karlsson\\'s',\n\t'two words': 1,\n}"
+    );
+    let options = WriteOptions::new().trailing_commas(true);
+    assert_eq!(to_string_with_options(&json!([1]), &options)?, "[1]");
+    Ok(())
+}
+
+#[test]
+fn it_escapes_strings_so_they_round_trip() -> Result<()> {
+    let value = json!([
+        "&{color.red}",
+        "a \"quote\"",
+        "back\\slash",
+        "new\nline\ttab\u{1}"
+    ]);
+    for quote in &['"', '\''] {
+        let options = WriteOptions::pretty().quote(*quote);
+        assert_eq!(parse(&to_string_with_options(&value, &options)?)?, value);
+    }
+    Ok(())
+}
+
+#[test]
+fn it_round_trips_parsed_documents() -> Result<()> {
+    let value = parse(indoc!(
+        r##"
+        {
+            // palette
+            color: { red: '#fed7d7', 'light green': "#c6f6d5" },
+            border: "1px solid &{color.red}",
+            escaped: '\&{color.red} isn\'t a reference',
+            sizes: [1, 2.5, -3e2, { nested: [] }],
+            nothing: null, enabled: true,
+        }
+        "##
+    ))?;
+    let options = WriteOptions::pretty()
+        .unquoted_keys(true)
+        .quote('\'')
+        .trailing_commas(true);
+    assert_eq!(parse(&to_string_with_options(&value, &options)?)?, value);
+    assert_eq!(parse(&to_string(&value)?)?, value);
+    Ok(())
+}
+
+#[test]
+fn it_serializes_structs() -> Result<()> {
+    let mut limits = BTreeMap::new();
+    limits.insert(1, Some('a'));
+    limits.insert(2, None);
+    let config = Config {
+        name: "app".to_string(),
+        port: 8080,
+        ratio: 0.5,
+        tags: vec![],
+        modes: vec![
+            Mode::Debug,
+            Mode::Level(2),
+            Mode::Range(-1, 1),
+            Mode::Release { optimize: true },
+        ],
+        limits,
+        empty: (),
+    };
+    let options = WriteOptions::pretty()
+        .unquoted_keys(true)
+        .quote('\'')
+        .trailing_commas(true);
+    let written = to_string_with_options(&config, &options)?;
+    assert_eq!(
+        written,
+        indoc!(
+            r#"
+            {
+                name: 'app',
+                port: 8080,
+                ratio: 0.5,
+                tags: [],
+                modes: [
+                    'Debug',
+                    {
+                        Level: 2,
+                    },
+                    {
+                        Range: [
+                            -1,
+                            1,
+                        ],
+                    },
+                    {
+                        Release: {
+                            optimize: true,
+                        },
+                    },
+                ],
+                limits: {
+                    1: 'a',
+                    2: null,
+                },
+                empty: null,
+            }"#
+        )
+    );
+    assert_eq!(from_str::<Config>(&written)?, config);
+    assert_eq!(
+        to_string(&config.modes)?,
+        r#"["Debug",{"Level":2},{"Range":[-1,1]},{"Release":{"optimize":true}}]"#
+    );
+    Ok(())
+}
+
+#[test]
+fn it_serializes_to_writers() -> Result<()> {
+    let mut output = Vec::new();
+    to_writer(&mut output, &vec![Mode::Debug])?;
+    assert_eq!(String::from_utf8(output)?, r#"["Debug"]"#);
+
+    let mut map = BTreeMap::new();
+    map.insert(vec![1], 1);
+    let error = to_string(&map).unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "key must be a string, number, bool or char"
+    );
+    Ok(())
+}